@@ -1,7 +1,130 @@
 use std::io::Write;
+use std::str::from_utf8;
+use std::str::FromStr;
 
+use atom_syndication::Entry;
+use atom_syndication::FixedDateTime;
+use chrono::Local;
 
+use crate::Error;
+use crate::ErrorKind;
+
+
+/// Storage for serialized entries, addressed by the entry key.
 pub trait Cache {
+    /// Start writing the content for the id, discarding any previous content.
     fn open(&mut self, id: String) -> &mut dyn Write;
+
+    /// Finish writing the content for the id.
     fn close(&mut self, id: String) -> usize;
+
+    /// The content stored for the id, or `None` if there is none.
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// The ids of all items in the cache, in no particular order.
+    fn list(&self) -> Result<Vec<String>, Error>;
+
+    /// Remove the content stored for the id. Returns false if there was none.
+    fn remove(&mut self, id: &str) -> Result<bool, Error>;
+
+    /// Whether content is stored for the id.
+    fn exists(&self, id: &str) -> bool;
+
+    /// Write out changes kept pending by the cache, if any.
+    fn sync(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// When the content for the id was last written, if known.
+    fn modified(&self, _id: &str) -> Option<FixedDateTime> {
+        None
+    }
+
+    /// When content for the id was first written, if known.
+    ///
+    /// Unlike `modified`, this is kept when the content is replaced. Defaults to `modified`.
+    fn seen(&self, id: &str) -> Option<FixedDateTime> {
+        self.modified(id)
+    }
+}
+
+/// The cache key of the entry with the given id and source, see `key_source`.
+pub(crate) fn source_key(source: &str, id: &str) -> String {
+    if source.is_empty() {
+        return String::from(id);
+    }
+    format!("{}:{} {}", source.len(), source, id)
+}
+
+/// The source part of the cache key of the entry with the given id.
+///
+/// The key is the entry id, preceded by the length of the source, a colon, the source and a
+/// space if there is a source. Returns `None` if the key does not belong to the entry id.
+pub(crate) fn key_source(key: &str, id: &str) -> Option<String> {
+    if key == id {
+        return Some(String::new());
+    }
+    let (l, v) = key.split_once(':')?;
+    let l: usize = l.parse().ok()?;
+    let source = v.get(..l)?;
+    if v[l..].strip_prefix(' ')? != id {
+        return None;
+    }
+    Some(String::from(source))
 }
+
+/// An entry read back from a cache, along with the source it was added from.
+pub struct CachedEntry {
+    pub entry: Entry,
+    pub source: String,
+    /// When the entry was first written to the cache.
+    pub seen: FixedDateTime,
+}
+
+impl CachedEntry {
+    /// Read back and parse the entry stored for the id, if any.
+    ///
+    /// The id is the key the entry was cached by.
+    pub fn from_cache(cache: &dyn Cache, id: &str) -> Result<Option<CachedEntry>, Error> {
+        let entry: Entry;
+        let source: String;
+
+        let b = match cache.get(id)? {
+            Some(v) => v,
+            None => {
+                return Ok(None);
+            },
+        };
+        let s = match from_utf8(b.as_slice()) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_uri(id).with_source(e));
+            },
+        };
+        match Entry::from_str(s) {
+            Ok(v) => {
+                entry = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_uri(id).with_source(e));
+            },
+        };
+
+        match key_source(id, &entry.id) {
+            Some(v) => {
+                source = v;
+            },
+            None => {
+                return Err(Error::new(ErrorKind::ParseError).with_uri(id).with_entry(&entry.id).with_source("cache key does not match entry id"));
+            },
+        };
+
+        Ok(Some(CachedEntry{
+            seen: cache.seen(id).unwrap_or_else(|| Local::now().fixed_offset()),
+            entry: entry,
+            source: source,
+        }))
+    }
+}
+
+pub mod retention;
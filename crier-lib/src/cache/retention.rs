@@ -0,0 +1,174 @@
+use std::str::from_utf8;
+use std::str::FromStr;
+use std::collections::HashSet;
+
+use atom_syndication::Entry;
+use atom_syndication::FixedDateTime;
+use chrono::Local;
+use chrono::TimeDelta;
+use log::debug;
+
+use super::Cache;
+use crate::Error;
+
+
+/// A cached item as seen by retention policies.
+pub struct CacheItem {
+    pub id: String,
+    /// The entry date; published if available, otherwise updated.
+    ///
+    /// Items that cannot be parsed as entries are dated by when they were written.
+    pub date: FixedDateTime,
+    pub size: usize,
+}
+
+/// Decides which cached items to evict.
+pub trait RetentionPolicy {
+    /// Return the ids of the items to evict. Items are given most recent first.
+    fn evict(&self, items: &[CacheItem]) -> Vec<String>;
+}
+
+/// Keep only the given number of most recent entries.
+pub struct MaxEntries {
+    max: usize,
+}
+
+/// Evict entries older than the given age at the time of collection.
+pub struct MaxAge {
+    max: TimeDelta,
+}
+
+/// Keep the most recent entries that fit within the given total size in bytes.
+pub struct MaxBytes {
+    max: usize,
+}
+
+impl MaxEntries {
+    pub fn new(max: usize) -> MaxEntries {
+        MaxEntries{
+            max: max,
+        }
+    }
+}
+
+impl MaxAge {
+    pub fn new(max: TimeDelta) -> MaxAge {
+        MaxAge{
+            max: max,
+        }
+    }
+}
+
+impl MaxBytes {
+    pub fn new(max: usize) -> MaxBytes {
+        MaxBytes{
+            max: max,
+        }
+    }
+}
+
+impl RetentionPolicy for MaxEntries {
+    fn evict(&self, items: &[CacheItem]) -> Vec<String> {
+        items.iter().skip(self.max).map(|v| v.id.clone()).collect()
+    }
+}
+
+impl RetentionPolicy for MaxAge {
+    /// Nothing is evicted if the age reaches back beyond the earliest representable date.
+    fn evict(&self, items: &[CacheItem]) -> Vec<String> {
+        let limit = match Local::now().fixed_offset().checked_sub_signed(self.max) {
+            Some(v) => v,
+            None => {
+                return Vec::new();
+            },
+        };
+        items.iter().filter(|v| v.date < limit).map(|v| v.id.clone()).collect()
+    }
+}
+
+impl RetentionPolicy for MaxBytes {
+    fn evict(&self, items: &[CacheItem]) -> Vec<String> {
+        let mut c: usize;
+        let mut r: Vec<String>;
+
+        c = 0;
+        r = Vec::new();
+        for v in items.iter() {
+            c += v.size;
+            if c > self.max {
+                r.push(v.id.clone());
+            }
+        }
+        r
+    }
+}
+
+fn item_date(cache: &dyn Cache, id: &str, b: &[u8]) -> FixedDateTime {
+    match from_utf8(b).ok().and_then(|s| Entry::from_str(s).ok()) {
+        Some(v) => {
+            match v.published {
+                Some(d) => {
+                    return d;
+                },
+                None => {
+                    return v.updated;
+                },
+            };
+        },
+        None => {},
+    };
+    match cache.modified(id) {
+        Some(v) => {
+            v
+        },
+        None => {
+            Local::now().fixed_offset()
+        },
+    }
+}
+
+/// The items of the cache, most recent first.
+pub fn items(cache: &dyn Cache) -> Result<Vec<CacheItem>, Error> {
+    let mut r: Vec<CacheItem>;
+
+    r = Vec::new();
+    for k in cache.list()? {
+        match cache.get(k.as_str())? {
+            Some(b) => {
+                r.push(CacheItem{
+                    date: item_date(cache, k.as_str(), b.as_slice()),
+                    size: b.len(),
+                    id: k,
+                });
+            },
+            None => {},
+        };
+    }
+    r.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.id.cmp(&b.id)));
+    Ok(r)
+}
+
+/// Remove the items of the cache selected by any of the policies.
+///
+/// Policies are applied in turn, each to the items the previous ones kept. Returns the ids of
+/// the evicted items.
+pub fn gc(cache: &mut dyn Cache, policies: &[&dyn RetentionPolicy]) -> Result<Vec<String>, Error> {
+    let mut r: Vec<String>;
+    let mut v: Vec<CacheItem>;
+
+    r = Vec::new();
+    v = items(cache)?;
+    for p in policies.iter() {
+        let evict = p.evict(v.as_slice());
+        let ids: HashSet<&String> = evict.iter().collect();
+        v.retain(|item| !ids.contains(&item.id));
+        r.extend(evict);
+    }
+
+    for k in r.iter() {
+        debug!("evicting {} from cache", k);
+        cache.remove(k.as_str())?;
+    }
+    cache.sync()?;
+    Ok(r)
+}
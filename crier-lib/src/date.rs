@@ -0,0 +1,362 @@
+use quick_xml::Reader;
+use quick_xml::Writer;
+use quick_xml::events::Event;
+use quick_xml::events::BytesText;
+
+use atom_syndication::FixedDateTime;
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+
+use crate::Error;
+use crate::ErrorKind;
+
+
+/// Formats with a numeric offset, tried after names and zones have been normalized.
+static FORMATS_ZONED: &[&str] = &[
+    "%d %b %Y %H:%M:%S %#z",
+    "%d %b %Y %H:%M %#z",
+    "%d %b %y %H:%M:%S %#z",
+    "%d %b %y %H:%M %#z",
+    "%d-%b-%y %H:%M:%S %#z",
+    "%b %d %Y %H:%M:%S %#z",
+    "%b %d, %Y %H:%M:%S %#z",
+    "%b %d %H:%M:%S %#z %Y",
+    "%Y-%m-%dT%H:%M:%S%.f%#z",
+    "%Y-%m-%dT%H:%M:%S%.f %#z",
+    "%Y-%m-%dT%H:%M%#z",
+    "%Y-%m-%d %H:%M:%S%.f%#z",
+    "%Y-%m-%d %H:%M:%S%.f %#z",
+    "%Y-%m-%d %H:%M%#z",
+    "%Y-%m-%d %H:%M %#z",
+];
+
+/// Formats without offset, taken as UTC.
+static FORMATS_NAIVE: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%d %b %Y %H:%M:%S",
+    "%d %b %Y %H:%M",
+    "%d %b %y %H:%M:%S",
+    "%d %b %y %H:%M",
+    "%b %d %Y %H:%M:%S",
+    "%b %d, %Y %H:%M:%S",
+    "%b %d, %Y %H:%M",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+    "%d. %b %Y %H:%M",
+];
+
+/// Formats without time of day, taken as midnight UTC.
+static FORMATS_DATE: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d %b %Y",
+    "%d %b %y",
+    "%b %d, %Y",
+    "%b %d %Y",
+    "%d.%m.%Y",
+    "%d. %b %Y",
+];
+
+/// Zone abbreviations and their offset in minutes.
+///
+/// Ambiguous abbreviations resolve to the North American zone, except IST which is India.
+static ZONES: &[(&str, i32)] = &[
+    ("UT", 0), ("UTC", 0), ("GMT", 0), ("Z", 0), ("WET", 0),
+    ("WEST", 60), ("BST", 60), ("CET", 60), ("MET", 60),
+    ("CEST", 120), ("MEST", 120), ("EET", 120), ("SAST", 120),
+    ("EEST", 180), ("MSK", 180),
+    ("IST", 330), ("PKT", 300),
+    ("ICT", 420), ("WIB", 420),
+    ("SGT", 480), ("HKT", 480), ("AWST", 480),
+    ("JST", 540), ("KST", 540),
+    ("ACST", 570), ("ACDT", 630),
+    ("AEST", 600), ("AEDT", 660),
+    ("NZST", 720), ("NZDT", 780),
+    ("HST", -600), ("AKST", -540), ("AKDT", -480),
+    ("PST", -480), ("PDT", -420),
+    ("MST", -420), ("MDT", -360),
+    ("CST", -360), ("CDT", -300),
+    ("EST", -300), ("EDT", -240),
+    ("AST", -240), ("ADT", -180),
+    ("NST", -210), ("NDT", -150),
+    ("BRT", -180), ("ART", -180),
+];
+
+/// Month names and abbreviations in english, german, french, spanish, italian and dutch.
+static MONTHS: &[(&str, &str)] = &[
+    ("january", "Jan"), ("jan", "Jan"), ("januar", "Jan"), ("jän", "Jan"), ("janvier", "Jan"),
+    ("janv", "Jan"), ("enero", "Jan"), ("ene", "Jan"), ("gennaio", "Jan"), ("gen", "Jan"),
+    ("januari", "Jan"),
+    ("february", "Feb"), ("feb", "Feb"), ("februar", "Feb"), ("février", "Feb"),
+    ("fevrier", "Feb"), ("févr", "Feb"), ("fevr", "Feb"), ("febrero", "Feb"),
+    ("febbraio", "Feb"), ("februari", "Feb"),
+    ("march", "Mar"), ("mar", "Mar"), ("märz", "Mar"), ("mär", "Mar"), ("mrz", "Mar"),
+    ("mars", "Mar"), ("marzo", "Mar"), ("maart", "Mar"), ("mrt", "Mar"),
+    ("april", "Apr"), ("apr", "Apr"), ("avril", "Apr"), ("avr", "Apr"), ("abril", "Apr"),
+    ("abr", "Apr"), ("aprile", "Apr"),
+    ("may", "May"), ("mai", "May"), ("mayo", "May"), ("maggio", "May"), ("mag", "May"),
+    ("mei", "May"),
+    ("june", "Jun"), ("jun", "Jun"), ("juni", "Jun"), ("juin", "Jun"), ("junio", "Jun"),
+    ("giugno", "Jun"), ("giu", "Jun"),
+    ("july", "Jul"), ("jul", "Jul"), ("juli", "Jul"), ("juillet", "Jul"), ("juil", "Jul"),
+    ("julio", "Jul"), ("luglio", "Jul"), ("lug", "Jul"),
+    ("august", "Aug"), ("aug", "Aug"), ("août", "Aug"), ("aout", "Aug"), ("agosto", "Aug"),
+    ("ago", "Aug"), ("augustus", "Aug"),
+    ("september", "Sep"), ("sep", "Sep"), ("sept", "Sep"), ("septembre", "Sep"),
+    ("septiembre", "Sep"), ("setiembre", "Sep"), ("settembre", "Sep"), ("set", "Sep"),
+    ("october", "Oct"), ("oct", "Oct"), ("oktober", "Oct"), ("okt", "Oct"),
+    ("octobre", "Oct"), ("octubre", "Oct"), ("ottobre", "Oct"), ("ott", "Oct"),
+    ("november", "Nov"), ("nov", "Nov"), ("novembre", "Nov"), ("noviembre", "Nov"),
+    ("december", "Dec"), ("dec", "Dec"), ("dezember", "Dec"), ("dez", "Dec"),
+    ("décembre", "Dec"), ("decembre", "Dec"), ("déc", "Dec"), ("diciembre", "Dec"),
+    ("dic", "Dec"), ("dicembre", "Dec"),
+];
+
+/// Weekday names and abbreviations, which carry no information once the date is known.
+static WEEKDAYS: &[&str] = &[
+    "mon", "monday", "tue", "tues", "tuesday", "wed", "wednesday", "thu", "thur", "thurs",
+    "thursday", "fri", "friday", "sat", "saturday", "sun", "sunday",
+    "mo", "montag", "di", "dienstag", "mi", "mittwoch", "do", "donnerstag", "fr", "freitag",
+    "sa", "samstag", "so", "sonntag",
+    "lun", "lundi", "mardi", "mer", "mercredi", "jeu", "jeudi", "ven", "vendredi", "sam",
+    "samedi", "dim", "dimanche",
+    "lunes", "martes", "miércoles", "miercoles", "jueves", "viernes", "sábado", "sabado",
+    "domingo",
+];
+
+fn offset_str(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    format!("{}{:02}{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+}
+
+/// offset for a zone abbreviation or a "GMT+2" style offset, in any case.
+fn zone(v: &str) -> Option<String> {
+    let u = v.to_ascii_uppercase();
+    for (name, minutes) in ZONES {
+        if u == *name {
+            return Some(offset_str(*minutes));
+        }
+    }
+    for prefix in ["GMT", "UTC", "UT"] {
+        let rest = match u.strip_prefix(prefix) {
+            Some(r) => r,
+            None => continue,
+        };
+        if !rest.starts_with('+') && !rest.starts_with('-') {
+            continue;
+        }
+        let digits = &rest[1..];
+        if !digits.is_empty() && digits.len() <= 2 && digits.chars().all(|c| c.is_ascii_digit()) {
+            return Some(format!("{}{:0>2}00", &rest[..1], digits));
+        }
+        return Some(String::from(rest));
+    }
+    None
+}
+
+fn month(v: &str) -> Option<&'static str> {
+    let s = v.trim_end_matches('.').to_lowercase();
+    MONTHS.iter().find(|(name, _)| *name == s).map(|(_, r)| *r)
+}
+
+/// rewrite the parts of the date chrono can not read into equivalents it can.
+fn normalize(v: &str) -> Vec<String> {
+    let mut r: Vec<String>;
+
+    r = Vec::new();
+    for (i, token) in v.split_whitespace().enumerate() {
+        let word = token.trim_end_matches(|c| c == ',' || c == '.');
+        let alpha = word.chars().all(|c| c.is_alphabetic());
+        if i == 0 && alpha && !word.is_empty() {
+            if token.ends_with(',') {
+                continue;
+            }
+            if month(word).is_none() && WEEKDAYS.contains(&word.to_lowercase().as_str()) {
+                continue;
+            }
+        }
+        if alpha {
+            match month(word) {
+                Some(m) => {
+                    r.push(format!("{}{}", m, &token[token.trim_end_matches(',').len()..]));
+                    continue;
+                },
+                None => {},
+            };
+        }
+        // words in lowercase are only taken as zone when they follow the time of day.
+        let after_time = r.last().is_some_and(|v| is_time_of_day(v));
+        if after_time || token.chars().all(|c| !c.is_alphabetic() || c.is_ascii_uppercase()) {
+            match zone(word) {
+                Some(z) => {
+                    r.push(z);
+                    continue;
+                },
+                None => {},
+            };
+        }
+        // ordinal days: 1st, 22nd, 3rd, 25th
+        let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let suffix = &word[digits.len()..];
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) && ["st", "nd", "rd", "th"].contains(&suffix.to_lowercase().as_str()) {
+            r.push(format!("{}{}", digits, &token[word.len()..]));
+            continue;
+        }
+        // zulu time glued to the time of day
+        if token.len() > 1 && token.ends_with('Z') && token[..token.len()-1].ends_with(|c: char| c.is_ascii_digit()) {
+            r.push(format!("{}+0000", &token[..token.len()-1]));
+            continue;
+        }
+        r.push(String::from(token));
+    }
+    r
+}
+
+/// true if the normalized token may be part of a time of day or zone, which trailing text
+/// never is.
+fn is_time_or_zone(v: &str) -> bool {
+    if v.contains(':') {
+        return true;
+    }
+    if ["am", "pm", "a.m.", "p.m."].contains(&v.to_lowercase().as_str()) {
+        return true;
+    }
+    match v.strip_prefix(|c| c == '+' || c == '-') {
+        Some(r) => {
+            !r.is_empty() && r.chars().all(|c| c.is_ascii_digit())
+        },
+        None => {
+            false
+        },
+    }
+}
+
+/// true if the token is a time of day without offset, like 20:46 or 20:46:00.123
+fn is_time_of_day(v: &str) -> bool {
+    v.contains(':') && v.chars().all(|c| c.is_ascii_digit() || c == ':' || c == '.')
+}
+
+/// true if the normalized token names a zone that is not known, judging by it following the
+/// time of day.
+fn is_unknown_zone(prev: &str, v: &str) -> bool {
+    is_time_of_day(prev) && v.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// four digit year formats also accept two digits, which are left to the two digit formats.
+fn parse_normalized(v: &str) -> Option<FixedDateTime> {
+    for f in FORMATS_ZONED {
+        match DateTime::parse_from_str(v, f) {
+            Ok(r) if r.year() >= 1000 => {
+                return Some(r);
+            },
+            _ => {},
+        };
+    }
+    for f in FORMATS_NAIVE {
+        match NaiveDateTime::parse_from_str(v, f) {
+            Ok(r) if r.year() >= 1000 => {
+                return Some(r.and_utc().fixed_offset());
+            },
+            _ => {},
+        };
+    }
+    for f in FORMATS_DATE {
+        match NaiveDate::parse_from_str(v, f) {
+            Ok(r) if r.year() >= 1000 => {
+                return Some(r.and_hms_opt(0, 0, 0).unwrap().and_utc().fixed_offset());
+            },
+            _ => {},
+        };
+    }
+    None
+}
+
+/// Parse a date as found in real world feeds.
+///
+/// Besides RFC 3339 and RFC 2822 this accepts zone abbreviations, missing seconds, two digit
+/// years, month names in any case and several languages, ordinal days, dates without time and
+/// trailing text. Dates without offset are taken as UTC, but a date with a zone name that is
+/// not known fails rather than being taken as UTC.
+pub fn parse(v: &str) -> Result<FixedDateTime, Error> {
+    let s = v.trim();
+    let tokens: Vec<String>;
+
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(r) => {
+            return Ok(r);
+        },
+        Err(_e) => {},
+    };
+    match DateTime::parse_from_rfc2822(s) {
+        Ok(r) if r.year() >= 1000 => {
+            return Ok(r);
+        },
+        _ => {},
+    };
+
+    tokens = normalize(s);
+    for i in (1..=tokens.len()).rev() {
+        match parse_normalized(tokens[..i].join(" ").as_str()) {
+            Some(r) => {
+                return Ok(r);
+            },
+            None => {},
+        };
+        // dropping an invalid time or zone would silently give a different date.
+        if is_time_or_zone(tokens[i-1].as_str()) || (i > 1 && is_unknown_zone(tokens[i-2].as_str(), tokens[i-1].as_str())) {
+            break;
+        }
+    }
+    Err(Error::new(ErrorKind::ParseError).with_source(format!("unrecognized date '{}'", v)))
+}
+
+/// Rewrite the dates of an atom document that do not parse as RFC 3339.
+///
+/// Dates that can not be parsed at all are left as they are.
+pub(crate) fn normalize_atom(b: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = Reader::from_reader(b);
+    let mut w = Writer::new(Vec::new());
+    let mut buf: Vec<u8> = Vec::new();
+    let mut in_date = false;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Eof) => {
+                break;
+            },
+            Ok(Event::Start(e)) => {
+                let n = e.local_name();
+                in_date = n.as_ref() == b"updated" || n.as_ref() == b"published";
+                w.write_event(Event::Start(e)).map_err(|e| Error::new(ErrorKind::ParseError).with_source(e))?;
+            },
+            Ok(Event::Text(e)) if in_date => {
+                let s = e.unescape().map_err(|e| Error::new(ErrorKind::ParseError).with_source(e))?.into_owned();
+                let r = match parse(s.as_str()) {
+                    Ok(v) => {
+                        BytesText::new(v.to_rfc3339().as_str()).into_owned()
+                    },
+                    Err(_e) => {
+                        e.into_owned()
+                    },
+                };
+                w.write_event(Event::Text(r)).map_err(|e| Error::new(ErrorKind::ParseError).with_source(e))?;
+            },
+            Ok(e) => {
+                in_date = false;
+                w.write_event(e).map_err(|e| Error::new(ErrorKind::ParseError).with_source(e))?;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_source(e));
+            },
+        };
+        buf.clear();
+    }
+    Ok(w.into_inner())
+}
@@ -0,0 +1,145 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+
+/// The category of failure, independent of where it happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    /// Output could not be produced or stored.
+    WriteError,
+    /// Entry cache could not be read or written.
+    CacheError,
+    /// A document or one of its values could not be understood.
+    ParseError,
+    /// A document lacks data required to process it.
+    IncompleteError,
+    /// Reading or writing a local resource failed.
+    IoError,
+    /// A remote source answered with the given unexpected status code.
+    StatusError(u16),
+    /// The target of a create operation already exists.
+    ExistsError,
+}
+
+/// Error type for all fallible operations in the crate.
+///
+/// Besides the kind of error, it carries the uri of the source or destination and the id of
+/// the entry being processed when known, and the underlying error that caused it.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    uri: Option<String>,
+    entry: Option<String>,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error{
+            kind: kind,
+            uri: None,
+            entry: None,
+            source: None,
+        }
+    }
+
+    /// Set the uri the error relates to, unless one has already been set.
+    pub fn with_uri(mut self, uri: &str) -> Error {
+        if self.uri.is_none() {
+            self.uri = Some(String::from(uri));
+        }
+        self
+    }
+
+    /// Set the id of the entry the error relates to, unless one has already been set.
+    pub fn with_entry(mut self, id: &str) -> Error {
+        if self.entry.is_none() && !id.is_empty() {
+            self.entry = Some(String::from(id));
+        }
+        self
+    }
+
+    /// Set the underlying cause. Anything convertible to a boxed error will do, including plain
+    /// strings.
+    pub fn with_source(mut self, source: impl Into<Box<dyn StdError + Send + Sync + 'static>>) -> Error {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+
+    pub fn entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::WriteError => write!(f, "cannot write output"),
+            ErrorKind::CacheError => write!(f, "cache failure"),
+            ErrorKind::ParseError => write!(f, "cannot parse"),
+            ErrorKind::IncompleteError => write!(f, "missing required data"),
+            ErrorKind::IoError => write!(f, "i/o failure"),
+            ErrorKind::StatusError(v) => write!(f, "unexpected status {}", v),
+            ErrorKind::ExistsError => write!(f, "already exists"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match &self.uri {
+            Some(v) => {
+                write!(f, " for {}", v)?;
+            },
+            None => {},
+        };
+        match &self.entry {
+            Some(v) => {
+                write!(f, " in entry {}", v)?;
+            },
+            None => {},
+        };
+        match &self.source {
+            Some(v) => {
+                write!(f, ": {}", v)?;
+            },
+            None => {},
+        };
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.source {
+            Some(v) => {
+                Some(v.as_ref())
+            },
+            None => {
+                None
+            },
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::new(ErrorKind::IoError).with_source(e)
+    }
+}
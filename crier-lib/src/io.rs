@@ -1,18 +1,72 @@
 use atom_syndication::Feed;
 
+use crate::Error;
+
 pub enum FeedMethod {
     Read,
     Create,
     Update,
 }
 
+/// Validators recorded for a source on its last successful retrieval.
+///
+/// Used with `FeedMethod::Update` to skip sources that have not changed since.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedState {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub mtime: Option<u64>,
+    pub digest: Option<String>,
+}
+
 pub trait FeedGet {
-    fn get(&self, s: &str, method: Option<FeedMethod>) -> Result<Feed, u64>;
+    fn get(&self, s: &str, method: Option<FeedMethod>) -> Result<Feed, Error>;
+
+    /// Retrieve the feed, consulting and refreshing the recorded state of the source.
+    ///
+    /// With `FeedMethod::Update`, `Ok(None)` is returned when the source is unchanged since
+    /// `state` was recorded. Any other method always retrieves and parses the source.
+    ///
+    /// By default the feed is always retrieved with `get`, and the state is left empty.
+    fn get_state(&self, s: &str, method: Option<FeedMethod>, state: &mut FeedState) -> Result<Option<Feed>, Error> {
+        *state = FeedState::default();
+        match self.get(s, method) {
+            Ok(v) => {
+                Ok(Some(v))
+            },
+            Err(e) => {
+                Err(e)
+            },
+        }
+    }
 }
 
 pub trait FeedPut {
-    fn put(&self, feed: &Feed, s: &str, method: Option<FeedMethod>) -> u64;
+    /// Store the feed at the given location.
+    ///
+    /// `FeedMethod::Create` fails if something already exists there, while
+    /// `FeedMethod::Update` (the default) replaces it.
+    fn put(&self, feed: &Feed, s: &str, method: Option<FeedMethod>) -> Result<(), Error>;
 }
 
+fn is_update(method: &Option<FeedMethod>) -> bool {
+    match method {
+        Some(FeedMethod::Update) => {
+            true
+        },
+        _ => {
+            false
+        },
+    }
+}
+
+pub mod state;
+
 #[cfg(feature = "fs")]
 pub mod fs;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
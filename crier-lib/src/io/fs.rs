@@ -1,68 +1,567 @@
 use std::path::Path;
 use std::path::PathBuf;
 use std::fs::File;
+use std::fs::metadata;
+use std::fs::rename;
+use std::fs::hard_link;
+use std::fs::remove_file;
+use std::fs::read_to_string;
+use std::fs::create_dir_all;
+use std::fs::OpenOptions;
+use std::fs::read;
+use std::fs::read_dir;
+use std::process;
+use std::io::Read;
+use std::time::UNIX_EPOCH;
 use std::collections::HashMap;
 use std::io::Write;
+use std::io;
+use std::str::from_utf8;
+use std::str::FromStr;
 
 use atom_syndication::Feed;
+use atom_syndication::Entry;
+use atom_syndication::FixedDateTime;
+use chrono::DateTime;
+use chrono::Local;
+use log::debug;
+use log::error;
 
 use super::FeedMethod;
 use super::FeedGet;
+use super::FeedPut;
+use super::FeedState;
+use super::is_update;
 use crate::cache::Cache;
-use crate::rss::from_file as rss_from_file;
+use crate::cache::source_key;
+use crate::digest_hex;
+use crate::Error;
+use crate::ErrorKind;
+use crate::rss::from_bytes as rss_from_bytes;
+use crate::DateFallback;
 
 
-pub struct FsFeed {
+/// Reads and writes feeds on the local filesystem.
+///
+/// Feeds are read as with a default `FsFeedReader`.
+pub struct FsFeed {}
+
+/// Reads feeds on the local filesystem, with settings for entries that can not be translated
+/// as they are.
+///
+/// With `with_allow_entry_fail`, entries that can not be translated are skipped instead of
+/// failing the whole feed.
+#[derive(Debug, Clone, Default)]
+pub struct FsFeedReader {
+    allow_entry_fail: bool,
+    date_fallback: DateFallback,
 }
 
 pub struct FsCache {
     dir: PathBuf,
     files: HashMap<String, File>,
+    index: HashMap<String, IndexItem>,
+    index_dirty: bool,
+    index_pending: Vec<String>,
+    failed: FailedWriter,
+}
+
+/// Stands in for a cache file that could not be created, failing every write with the error.
+struct FailedWriter {
+    kind: io::ErrorKind,
+    message: String,
+}
+
+impl Write for FailedWriter {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, io::Error> {
+        Err(io::Error::new(self.kind, self.message.clone()))
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// The id of a cached item, and when it was first written.
+struct IndexItem {
+    id: String,
+    seen: Option<FixedDateTime>,
+}
+
+impl FsFeedReader {
+    pub fn new() -> FsFeedReader {
+        FsFeedReader::default()
+    }
+
+    pub fn with_allow_entry_fail(mut self, allow: bool) -> FsFeedReader {
+        self.allow_entry_fail = allow;
+        self
+    }
+
+    pub fn with_date_fallback(mut self, fallback: DateFallback) -> FsFeedReader {
+        self.date_fallback = fallback;
+        self
+    }
 }
 
 impl FeedGet for FsFeed {
-    fn get(&self, s: &str, _method: Option<FeedMethod>) -> Result<Feed, u64> {
+    fn get(&self, s: &str, method: Option<FeedMethod>) -> Result<Feed, Error> {
+        FsFeedReader::new().get(s, method)
+    }
+
+    fn get_state(&self, s: &str, method: Option<FeedMethod>, state: &mut FeedState) -> Result<Option<Feed>, Error> {
+        FsFeedReader::new().get_state(s, method, state)
+    }
+}
+
+impl FeedGet for FsFeedReader {
+    fn get(&self, s: &str, method: Option<FeedMethod>) -> Result<Feed, Error> {
+        let mut state = FeedState::default();
+        match self.get_state(s, method, &mut state) {
+            Ok(Some(v)) => {
+                Ok(v)
+            },
+            Ok(None) => {
+                Err(Error::new(ErrorKind::IncompleteError).with_uri(s))
+            },
+            Err(e) => {
+                Err(e)
+            },
+        }
+    }
+
+    fn get_state(&self, s: &str, method: Option<FeedMethod>, state: &mut FeedState) -> Result<Option<Feed>, Error> {
         let feed: Feed;
-        match rss_from_file(s, false) {
+        let mtime: Option<u64>;
+        let digest: String;
+        let mut b: Vec<u8>;
+        let update = is_update(&method);
+
+        mtime = match metadata(s) {
+            Ok(v) => {
+                match v.modified() {
+                    Ok(t) => {
+                        t.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+                    },
+                    Err(_e) => {
+                        None
+                    },
+                }
+            },
+            Err(e) => {
+                return Err(Error::from(e).with_uri(s));
+            },
+        };
+        if update && mtime.is_some() && state.mtime == mtime {
+            debug!("{} not modified since {:?}", s, mtime);
+            return Ok(None);
+        }
+
+        b = Vec::new();
+        match File::open(s) {
+            Ok(mut f) => {
+                match f.read_to_end(&mut b) {
+                    Ok(_) => {},
+                    Err(e) => {
+                        return Err(Error::from(e).with_uri(s));
+                    },
+                };
+            },
+            Err(e) => {
+                return Err(Error::from(e).with_uri(s));
+            },
+        };
+
+        digest = digest_hex(b.as_slice());
+        if update && state.digest.as_ref() == Some(&digest) {
+            debug!("{} content unchanged", s);
+            state.mtime = mtime;
+            return Ok(None);
+        }
+
+        match rss_from_bytes(b.as_slice(), s, self.allow_entry_fail, self.date_fallback) {
             Ok(v) => {
                 feed = v;
             },
             Err(e) => {
-                return Err(0);
+                return Err(e.with_uri(s));
+            },
+        };
+        state.mtime = mtime;
+        state.digest = Some(digest);
+        Ok(Some(feed))
+    }
+}
+
+impl FeedPut for FsFeed {
+    /// See `FsFeed::put_bytes`.
+    fn put(&self, feed: &Feed, s: &str, method: Option<FeedMethod>) -> Result<(), Error> {
+        let b: Vec<u8>;
+
+        match feed.write_to(Vec::new()) {
+            Ok(v) => {
+                b = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e));
+            },
+        };
+        self.put_bytes(b.as_slice(), s, method)
+    }
+}
+
+impl FsFeed {
+    /// Store an already serialized document at the given location, with the same semantics as
+    /// `FeedPut::put`.
+    ///
+    /// The document is written to a temporary file in the same directory, which then
+    /// atomically takes the place of the destination.
+    pub fn put_bytes(&self, b: &[u8], s: &str, method: Option<FeedMethod>) -> Result<(), Error> {
+        let p: &Path;
+        let fp_tmp: PathBuf;
+        let mut f: File;
+        let create: bool;
+
+        create = match method {
+            Some(FeedMethod::Create) => {
+                true
+            },
+            Some(FeedMethod::Read) => {
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source("cannot put with read method"));
+            },
+            _ => {
+                false
+            },
+        };
+
+        p = Path::new(s);
+        if create && p.exists() {
+            return Err(Error::new(ErrorKind::ExistsError).with_uri(s));
+        }
+
+        fp_tmp = match p.file_name() {
+            Some(v) => {
+                p.with_file_name(format!(".{}.{}.tmp", v.to_string_lossy(), process::id()))
+            },
+            None => {
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source("not a file path"));
+            },
+        };
+
+        match File::create(&fp_tmp) {
+            Ok(v) => {
+                f = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e));
+            },
+        };
+
+        match f.write_all(b) {
+            Ok(_) => {},
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e));
+            },
+        };
+        match f.sync_all() {
+            Ok(_) => {},
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                return Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e));
             },
         };
-        Ok(feed)
+
+        if create {
+            // linking fails if the destination appeared in the meantime, rename would clobber it.
+            let r = hard_link(&fp_tmp, p);
+            let _ = remove_file(&fp_tmp);
+            return match r {
+                Ok(_) => {
+                    Ok(())
+                },
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::AlreadyExists {
+                        return Err(Error::new(ErrorKind::ExistsError).with_uri(s));
+                    }
+                    Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e))
+                },
+            };
+        }
+
+        match rename(&fp_tmp, p) {
+            Ok(_) => {
+                Ok(())
+            },
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                Err(Error::new(ErrorKind::WriteError).with_uri(s).with_source(e))
+            },
+        }
+    }
+}
+
+static INDEX_FILE: &str = "index";
+/// Marks a cache directory as holding no entries in the earlier flat layout.
+static MIGRATED_FILE: &str = ".migrated";
+
+/// the key of an entry in the earlier flat cache layout, from its file name.
+fn name_to_key(name: &str) -> String {
+    name.replace("%2F", "/").replace("%5C", "\\")
+}
+
+fn escape_id(id: &str) -> String {
+    id.replace("\\", "\\\\").replace("\t", "\\t").replace("\n", "\\n")
+}
+
+fn unescape_id(v: &str) -> String {
+    let mut r: String;
+    let mut escaped: bool;
+
+    r = String::new();
+    escaped = false;
+    for c in v.chars() {
+        if escaped {
+            match c {
+                't' => r.push('\t'),
+                'n' => r.push('\n'),
+                _ => r.push(c),
+            };
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else {
+            r.push(c);
+        }
     }
+    r
 }
 
 impl FsCache {
+    /// Open the cache in the given directory.
+    ///
+    /// Each item is stored in a file named by the SHA-512 digest of its id, in a subdirectory
+    /// named by the first two digits of the digest. The `index` file in the directory maps the
+    /// digests back to the ids, and records when each id was first written.
     pub fn new(path: PathBuf) -> FsCache {
-        FsCache{
+        let mut o = FsCache{
             dir: path,
             files: HashMap::new(),
+            index: HashMap::new(),
+            index_dirty: false,
+            index_pending: vec!(),
+            failed: FailedWriter{
+                kind: io::ErrorKind::Other,
+                message: String::new(),
+            },
+        };
+
+        let s = match read_to_string(o.dir.join(INDEX_FILE)) {
+            Ok(v) => v,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    error!("cannot read cache index in {:?}: {}", o.dir, e);
+                }
+                String::new()
+            },
+        };
+        for l in s.lines() {
+            let v: Vec<&str> = l.splitn(3, '\t').collect();
+            if v.len() < 2 {
+                error!("skipping invalid cache index line: {}", l);
+                continue;
+            }
+            o.index.insert(String::from(v[0]), IndexItem{
+                id: unescape_id(v[1]),
+                seen: v.get(2).and_then(|s| DateTime::parse_from_rfc3339(s).ok()),
+            });
+        }
+        o.migrate_flat();
+        o
+    }
+
+    /// Move entries cached in the earlier flat layout, one file per entry named by its key
+    /// directly in the cache directory, to their place in the current layout.
+    ///
+    /// The file time is kept as the time the entry was first seen. Files that do not hold an
+    /// entry are left alone. Once all entries are moved, the directory is marked so that it is
+    /// not scanned again.
+    fn migrate_flat(&mut self) {
+        let mut migrated: bool;
+
+        if self.dir.join(MIGRATED_FILE).exists() {
+            return;
+        }
+        let it = match read_dir(&self.dir) {
+            Ok(v) => v,
+            Err(_e) => {
+                return;
+            },
+        };
+
+        migrated = true;
+
+        for v in it.flatten() {
+            let key: String;
+            let name = v.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') || name == INDEX_FILE || !v.path().is_file() {
+                continue;
+            }
+            let b = match read(v.path()) {
+                Ok(b) => b,
+                Err(e) => {
+                    error!("cannot read cache file {:?}: {}", v.path(), e);
+                    migrated = false;
+                    continue;
+                },
+            };
+            let entry = match from_utf8(b.as_slice()).ok().and_then(|s| Entry::from_str(s).ok()) {
+                Some(entry) => entry,
+                None => {
+                    debug!("not migrating cache file {:?}, not an entry", v.path());
+                    continue;
+                },
+            };
+
+            // the flat layout keyed entries by the source, a space and the id.
+            let name_key = name_to_key(name.as_str());
+            key = match name_key.strip_suffix(format!(" {}", entry.id).as_str()) {
+                Some(source) => {
+                    source_key(source, entry.id.as_str())
+                },
+                None => {
+                    name_key
+                },
+            };
+            let seen = v.metadata().and_then(|m| m.modified()).ok().map(|t| DateTime::<Local>::from(t).fixed_offset());
+
+            let fp = self.path(key.as_str());
+            let r = create_dir_all(fp.parent().unwrap()).and_then(|_| rename(v.path(), &fp));
+            match r {
+                Ok(_) => {
+                    debug!("migrated cache file {:?} to {:?}", v.path(), fp);
+                    self.index.insert(digest_hex(key.as_bytes()), IndexItem{
+                        id: key,
+                        seen: seen,
+                    });
+                    self.index_dirty = true;
+                },
+                Err(e) => {
+                    error!("cannot migrate cache file {:?}: {}", v.path(), e);
+                    migrated = false;
+                },
+            };
+        }
+        match self.sync() {
+            Ok(_) => {},
+            Err(e) => {
+                error!("{}", e);
+                return;
+            },
+        };
+        if migrated {
+            match File::create(self.dir.join(MIGRATED_FILE)) {
+                Ok(_) => {},
+                Err(e) => {
+                    error!("cannot mark cache {:?} as migrated: {}", self.dir, e);
+                },
+            };
+        }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        let digest = digest_hex(id.as_bytes());
+        self.dir.join(&digest[..2]).join(digest)
+    }
+
+    /// record a new id in the index, to be written on the next sync.
+    fn index_add(&mut self, id: &str) {
+        let digest = digest_hex(id.as_bytes());
+        if self.index.contains_key(&digest) {
+            return;
+        }
+        self.index.insert(digest.clone(), IndexItem{
+            id: String::from(id),
+            seen: Some(Local::now().fixed_offset()),
+        });
+        self.index_pending.push(digest);
+    }
+
+    /// append the ids added since the index was last written, in a single write.
+    fn index_append(&self) -> Result<(), io::Error> {
+        let mut s: String;
+
+        s = String::new();
+        for k in self.index_pending.iter() {
+            match self.index.get(k) {
+                Some(v) => {
+                    s.push_str(format!("{}\t{}\t{}\n", k, escape_id(v.id.as_str()), v.seen.unwrap().to_rfc3339()).as_str());
+                },
+                None => {},
+            };
+        }
+        create_dir_all(&self.dir)?;
+        let mut f = OpenOptions::new().create(true).append(true).open(self.dir.join(INDEX_FILE))?;
+        f.write_all(s.as_bytes())?;
+        f.sync_all()
+    }
+
+    fn index_save(&self) -> Result<(), io::Error> {
+        let fp = self.dir.join(INDEX_FILE);
+        let fp_tmp = self.dir.join(format!(".{}.{}.tmp", INDEX_FILE, process::id()));
+        let mut f = File::create(&fp_tmp)?;
+        for (k, v) in self.index.iter() {
+            // ids removed by another instance of the cache are dropped.
+            if !self.exists(v.id.as_str()) {
+                continue;
+            }
+            match v.seen {
+                Some(t) => {
+                    writeln!(f, "{}\t{}\t{}", k, escape_id(v.id.as_str()), t.to_rfc3339())?;
+                },
+                None => {
+                    writeln!(f, "{}\t{}", k, escape_id(v.id.as_str()))?;
+                },
+            };
+        }
+        f.sync_all()?;
+        rename(&fp_tmp, &fp)
+    }
+
+    fn create(&mut self, id: &str) -> Result<File, Error> {
+        let fp: PathBuf;
+
+        self.index_add(id);
+        fp = self.path(id);
+        match create_dir_all(fp.parent().unwrap()).and_then(|_| File::create(&fp)) {
+            Ok(v) => {
+                Ok(v)
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_uri(&fp.to_string_lossy()).with_entry(id).with_source(e))
+            },
         }
     }
 }
 
 impl Cache for FsCache {
+    /// If the file for the id can not be created, writes to the returned writer fail with the
+    /// error.
     fn open(&mut self, id: String) -> &mut dyn Write {
-        let p: &Path;
-        let fp: PathBuf;
-        let mut s: String;
-        let mut ids: String;
-        let mut f: File;
-
         if !self.files.contains_key(&id) {
-            p = Path::new(self.dir.as_path());
-       
-            ids = id.clone();
-            ids = ids.replace("/", "%2F");
-            ids = ids.replace("\\", "%5C");
-
-            fp = p.join(ids);
-            s = String::from(fp.to_str().unwrap());
-            f = File::create(s.as_str()).unwrap();
-            self.files.insert(id.clone(), f);
+            match self.create(id.as_str()) {
+                Ok(v) => {
+                    self.files.insert(id.clone(), v);
+                },
+                Err(e) => {
+                    error!("{}", e);
+                    self.failed = FailedWriter{
+                        kind: io::ErrorKind::Other,
+                        message: e.to_string(),
+                    };
+                    return &mut self.failed;
+                },
+            };
         }
         return self.files.get_mut(&id).unwrap();
     }
@@ -73,4 +572,109 @@ impl Cache for FsCache {
         }
         0
     }
+
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, Error> {
+        match read(self.path(id)) {
+            Ok(v) => {
+                Ok(Some(v))
+            },
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    return Ok(None);
+                }
+                Err(Error::new(ErrorKind::CacheError).with_uri(id).with_source(e))
+            },
+        }
+    }
+
+    /// Only ids recorded in the index with an existing file are listed.
+    fn list(&self) -> Result<Vec<String>, Error> {
+        Ok(self.index.values().filter(|v| self.exists(v.id.as_str())).map(|v| v.id.clone()).collect())
+    }
+
+    fn remove(&mut self, id: &str) -> Result<bool, Error> {
+        let r: bool;
+
+        self.files.remove(id);
+        match remove_file(self.path(id)) {
+            Ok(_) => {
+                r = true;
+            },
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(Error::new(ErrorKind::CacheError).with_uri(id).with_source(e));
+                }
+                r = false;
+            },
+        };
+
+        if self.index.remove(&digest_hex(id.as_bytes())).is_some() {
+            self.index_dirty = true;
+        }
+        Ok(r)
+    }
+
+    /// Write the index if items were added or removed since it was last written. Added items
+    /// are appended, while removals rewrite the whole index.
+    ///
+    /// This is also done when the cache is dropped, but errors are then only logged.
+    fn sync(&mut self) -> Result<(), Error> {
+        let r: Result<(), io::Error>;
+
+        if self.index_dirty {
+            r = self.index_save();
+        } else if !self.index_pending.is_empty() {
+            r = self.index_append();
+        } else {
+            return Ok(());
+        }
+        match r {
+            Ok(_) => {
+                self.index_dirty = false;
+                self.index_pending.clear();
+                Ok(())
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_uri(&self.dir.join(INDEX_FILE).to_string_lossy()).with_source(e))
+            },
+        }
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        self.path(id).is_file()
+    }
+
+    fn modified(&self, id: &str) -> Option<FixedDateTime> {
+        match metadata(self.path(id)).and_then(|m| m.modified()) {
+            Ok(t) => {
+                Some(DateTime::<Local>::from(t).fixed_offset())
+            },
+            Err(_e) => {
+                None
+            },
+        }
+    }
+
+    /// Items indexed before first-seen times were recorded fall back to the file time.
+    fn seen(&self, id: &str) -> Option<FixedDateTime> {
+        match self.index.get(&digest_hex(id.as_bytes())).and_then(|v| v.seen) {
+            Some(v) => {
+                Some(v)
+            },
+            None => {
+                self.modified(id)
+            },
+        }
+    }
+}
+
+impl Drop for FsCache {
+    fn drop(&mut self) {
+        match self.sync() {
+            Ok(_) => {},
+            Err(e) => {
+                error!("{}", e);
+            },
+        };
+    }
 }
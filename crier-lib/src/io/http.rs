@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use http::Uri;
+use http::StatusCode;
+use http::header::ACCEPT;
+use http::header::CONTENT_TYPE;
+use http::header::ETAG;
+use http::header::LAST_MODIFIED;
+use http::header::IF_NONE_MATCH;
+use http::header::IF_MODIFIED_SINCE;
+use http::HeaderValue;
+use ureq::Agent;
+use ureq::ResponseExt;
+use log::debug;
+
+use atom_syndication::Feed;
+
+use super::FeedMethod;
+use super::FeedGet;
+use super::FeedState;
+use super::is_update;
+use crate::digest_hex;
+use crate::Error;
+use crate::ErrorKind;
+use crate::rss::from_bytes as rss_from_bytes;
+use crate::DateFallback;
+
+static ACCEPT_FEED: &str = "application/atom+xml, application/rss+xml, application/rdf+xml;q=0.9, application/xml;q=0.8, text/xml;q=0.8, */*;q=0.1";
+
+/// Default time allowed for a complete request, including redirects and body.
+pub static DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of redirects followed before giving up.
+pub static DEFAULT_MAX_REDIRECTS: u32 = 5;
+
+/// Largest response body accepted, in bytes.
+pub static DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+
+/// Retrieves feeds over http and https.
+///
+/// If the server answers with an unexpected status, the error kind is `StatusError` with the
+/// status code of the final response.
+pub struct HttpFeed {
+    timeout: Duration,
+    max_redirects: u32,
+    max_size: u64,
+    allow_entry_fail: bool,
+    date_fallback: DateFallback,
+}
+
+impl HttpFeed {
+    pub fn new() -> HttpFeed {
+        HttpFeed{
+            timeout: DEFAULT_TIMEOUT,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_size: DEFAULT_MAX_SIZE,
+            allow_entry_fail: false,
+            date_fallback: DateFallback::Reject,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> HttpFeed {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_redirects(mut self, max_redirects: u32) -> HttpFeed {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> HttpFeed {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn with_allow_entry_fail(mut self, allow: bool) -> HttpFeed {
+        self.allow_entry_fail = allow;
+        self
+    }
+
+    pub fn with_date_fallback(mut self, fallback: DateFallback) -> HttpFeed {
+        self.date_fallback = fallback;
+        self
+    }
+
+    fn agent(&self) -> Agent {
+        Agent::config_builder()
+            .timeout_global(Some(self.timeout))
+            .max_redirects(self.max_redirects)
+            .http_status_as_error(false)
+            .user_agent(format!("crier/{}", env!("CARGO_PKG_VERSION")))
+            .build()
+            .into()
+    }
+}
+
+impl Default for HttpFeed {
+    fn default() -> HttpFeed {
+        HttpFeed::new()
+    }
+}
+
+/// true if uri uses a scheme this module can retrieve.
+pub fn is_http(s: &str) -> bool {
+    match s.parse::<Uri>() {
+        Ok(v) => {
+            match v.scheme_str() {
+                Some("http") | Some("https") => {
+                    return v.host().is_some();
+                },
+                _ => {},
+            }
+        },
+        Err(_e) => {},
+    };
+    false
+}
+
+/// reject content types that can not possibly be a feed document.
+///
+/// Servers are notoriously sloppy with feed media types, so anything xml, json or unspecified
+/// is given a chance in the parser.
+fn check_content_type(v: Option<&str>) -> bool {
+    let mut mime: String;
+
+    match v {
+        Some(s) => {
+            mime = String::from(s.split(';').next().unwrap_or(""));
+            mime = mime.trim().to_lowercase();
+        },
+        None => {
+            return true;
+        },
+    };
+
+    match mime.as_str() {
+        "" | "text/plain" | "application/octet-stream" => {
+            return true;
+        },
+        _ => {},
+    };
+
+    mime.ends_with("/xml") || mime.ends_with("+xml") || mime.ends_with("/json") || mime.ends_with("+json")
+}
+
+impl FeedGet for HttpFeed {
+    fn get(&self, s: &str, method: Option<FeedMethod>) -> Result<Feed, Error> {
+        let mut state = FeedState::default();
+        match self.get_state(s, method, &mut state) {
+            Ok(Some(v)) => {
+                Ok(v)
+            },
+            Ok(None) => {
+                Err(Error::new(ErrorKind::StatusError(StatusCode::NOT_MODIFIED.as_u16())).with_uri(s))
+            },
+            Err(e) => {
+                Err(e)
+            },
+        }
+    }
+
+    fn get_state(&self, s: &str, method: Option<FeedMethod>, state: &mut FeedState) -> Result<Option<Feed>, Error> {
+        let status: StatusCode;
+        let b: Vec<u8>;
+        let digest: String;
+        let etag: Option<String>;
+        let last_modified: Option<String>;
+        let update = is_update(&method);
+
+        if !is_http(s) {
+            return Err(Error::new(ErrorKind::IoError).with_uri(s).with_source("not a http uri"));
+        }
+
+        let agent = self.agent();
+        let mut q = agent.get(s).header(ACCEPT, ACCEPT_FEED);
+        if update {
+            match &state.etag {
+                Some(v) => {
+                    q = q.header(IF_NONE_MATCH, v.as_str());
+                },
+                None => {},
+            };
+            match &state.last_modified {
+                Some(v) => {
+                    q = q.header(IF_MODIFIED_SINCE, v.as_str());
+                },
+                None => {},
+            };
+        }
+
+        let mut r = match q.call() {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::new(ErrorKind::IoError).with_uri(s).with_source(e));
+            },
+        };
+
+        status = r.status();
+        if r.get_uri().to_string() != s {
+            debug!("{} redirected to {}", s, r.get_uri());
+        }
+        if update && status == StatusCode::NOT_MODIFIED {
+            debug!("{} not modified", s);
+            // the server may send new validators with the response, which still apply.
+            match header_string(r.headers().get(ETAG)) {
+                Some(v) => {
+                    state.etag = Some(v);
+                },
+                None => {},
+            };
+            match header_string(r.headers().get(LAST_MODIFIED)) {
+                Some(v) => {
+                    state.last_modified = Some(v);
+                },
+                None => {},
+            };
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(Error::new(ErrorKind::StatusError(status.as_u16())).with_uri(s));
+        }
+
+        match r.headers().get(CONTENT_TYPE) {
+            Some(v) => {
+                if !check_content_type(v.to_str().ok()) {
+                    return Err(Error::new(ErrorKind::ParseError).with_uri(s).with_source(format!("unsupported content type {:?}", v)));
+                }
+            },
+            None => {},
+        };
+
+        etag = header_string(r.headers().get(ETAG));
+        last_modified = header_string(r.headers().get(LAST_MODIFIED));
+
+        match r.body_mut().with_config().limit(self.max_size).read_to_vec() {
+            Ok(v) => {
+                b = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::IoError).with_uri(s).with_source(e));
+            },
+        };
+
+        digest = digest_hex(b.as_slice());
+        if update && state.digest.as_ref() == Some(&digest) {
+            debug!("{} content unchanged", s);
+            state.etag = etag;
+            state.last_modified = last_modified;
+            return Ok(None);
+        }
+
+        // the validators are only recorded once the content they describe has been read.
+        match rss_from_bytes(b.as_slice(), s, self.allow_entry_fail, self.date_fallback) {
+            Ok(v) => {
+                state.etag = etag;
+                state.last_modified = last_modified;
+                state.digest = Some(digest);
+                Ok(Some(v))
+            },
+            Err(e) => {
+                Err(e.with_uri(s))
+            },
+        }
+    }
+}
+
+fn header_string(v: Option<&HeaderValue>) -> Option<String> {
+    match v {
+        Some(vv) => {
+            vv.to_str().ok().map(|s| String::from(s))
+        },
+        None => {
+            None
+        },
+    }
+}
@@ -0,0 +1,269 @@
+use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::from_utf8;
+use std::str::FromStr;
+
+use atom_syndication::Entry;
+use atom_syndication::FixedDateTime;
+use chrono::DateTime;
+use chrono::Local;
+use log::error;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use rusqlite::params;
+
+use crate::cache::Cache;
+use crate::cache::key_source;
+use crate::digest_hex;
+use crate::Error;
+use crate::ErrorKind;
+
+
+static SCHEMA: &str = "CREATE TABLE IF NOT EXISTS entry (
+    id TEXT PRIMARY KEY NOT NULL,
+    digest TEXT NOT NULL,
+    source TEXT NOT NULL,
+    published TEXT,
+    updated TEXT,
+    modified TEXT NOT NULL,
+    seen TEXT,
+    data BLOB NOT NULL
+)";
+
+/// Databases created before first-seen times were recorded lack the column.
+static MIGRATE_SEEN: &str = "ALTER TABLE entry ADD COLUMN seen TEXT";
+
+/// Cache storing entries in a table of an SQLite database.
+///
+/// Alongside the serialized entry, each row holds its key, the digest of the key, the source
+/// the entry dates, and when the key was last and first written.
+///
+/// Content is buffered from `open` and stored on `close`. Changes are made in a single
+/// transaction, committed on `sync` or when the cache is dropped.
+pub struct SqliteCache {
+    conn: Connection,
+    pending: HashMap<String, Vec<u8>>,
+    failed: Option<Error>,
+}
+
+impl SqliteCache {
+    /// Open or create the database at the given path.
+    pub fn new(path: &Path) -> Result<SqliteCache, Error> {
+        let conn: Connection;
+
+        match Connection::open(path) {
+            Ok(v) => {
+                conn = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_uri(&path.to_string_lossy()).with_source(e));
+            },
+        };
+        match conn.execute(SCHEMA, ()) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_uri(&path.to_string_lossy()).with_source(e));
+            },
+        };
+        if conn.prepare("SELECT seen FROM entry LIMIT 0").is_err() {
+            match conn.execute(MIGRATE_SEEN, ()) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(Error::new(ErrorKind::CacheError).with_uri(&path.to_string_lossy()).with_source(e));
+                },
+            };
+        }
+        Ok(SqliteCache{
+            conn: conn,
+            pending: HashMap::new(),
+            failed: None,
+        })
+    }
+
+    /// start the transaction holding the changes, unless one is already open.
+    fn begin(&self) -> Result<(), Error> {
+        if !self.conn.is_autocommit() {
+            return Ok(());
+        }
+        match self.conn.execute_batch("BEGIN") {
+            Ok(_) => {
+                Ok(())
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_source(e))
+            },
+        }
+    }
+
+    fn store(&self, id: &str, b: &[u8]) -> Result<(), Error> {
+        let mut source: String = String::new();
+        let mut published: Option<String> = None;
+        let mut updated: Option<String> = None;
+
+        // dates and source are only recorded for content that parses as an entry.
+        match from_utf8(b).ok().and_then(|s| Entry::from_str(s).ok()) {
+            Some(v) => {
+                source = key_source(id, &v.id).unwrap_or_default();
+                published = v.published.map(|d| d.to_rfc3339());
+                updated = Some(v.updated.to_rfc3339());
+            },
+            None => {},
+        };
+
+        self.begin()?;
+
+        // the first-seen time is kept when the row is replaced.
+        let now = Local::now().fixed_offset().to_rfc3339();
+        match self.conn.execute(
+            "INSERT INTO entry (id, digest, source, published, updated, modified, seen, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7) \
+                ON CONFLICT(id) DO UPDATE SET digest = excluded.digest, source = excluded.source, published = excluded.published, \
+                updated = excluded.updated, modified = excluded.modified, seen = COALESCE(entry.seen, entry.modified), data = excluded.data",
+            params![id, digest_hex(id.as_bytes()), source, published, updated, now, b],
+        ) {
+            Ok(_) => {
+                Ok(())
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_uri(id).with_source(e))
+            },
+        }
+    }
+}
+
+impl Cache for SqliteCache {
+    fn open(&mut self, id: String) -> &mut dyn Write {
+        self.pending.insert(id.clone(), Vec::new());
+        self.pending.get_mut(&id).unwrap()
+    }
+
+    /// If the content can not be stored, the error is returned by the next `sync`.
+    fn close(&mut self, id: String) -> usize {
+        let b = match self.pending.remove(&id) {
+            Some(v) => v,
+            None => {
+                return 0;
+            },
+        };
+        match self.store(id.as_str(), b.as_slice()) {
+            Ok(_) => {
+                b.len()
+            },
+            Err(e) => {
+                error!("{}", e);
+                if self.failed.is_none() {
+                    self.failed = Some(e);
+                }
+                0
+            },
+        }
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.conn.query_row("SELECT data FROM entry WHERE id = ?1", params![id], |r| r.get(0)).optional() {
+            Ok(v) => {
+                Ok(v)
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_uri(id).with_source(e))
+            },
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>, Error> {
+        let mut r: Vec<String>;
+
+        r = Vec::new();
+        let mut stmt = match self.conn.prepare("SELECT id FROM entry") {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_source(e));
+            },
+        };
+        let rows = match stmt.query_map((), |row| row.get(0)) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_source(e));
+            },
+        };
+        for v in rows {
+            match v {
+                Ok(id) => {
+                    r.push(id);
+                },
+                Err(e) => {
+                    return Err(Error::new(ErrorKind::CacheError).with_source(e));
+                },
+            };
+        }
+        Ok(r)
+    }
+
+    fn remove(&mut self, id: &str) -> Result<bool, Error> {
+        self.pending.remove(id);
+        self.begin()?;
+        match self.conn.execute("DELETE FROM entry WHERE id = ?1", params![id]) {
+            Ok(v) => {
+                Ok(v > 0)
+            },
+            Err(e) => {
+                Err(Error::new(ErrorKind::CacheError).with_uri(id).with_source(e))
+            },
+        }
+    }
+
+    /// Commit the changes made since the last sync.
+    ///
+    /// If storing any content failed since, the first error is returned after committing the
+    /// rest.
+    fn sync(&mut self) -> Result<(), Error> {
+        if !self.conn.is_autocommit() {
+            match self.conn.execute_batch("COMMIT") {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(Error::new(ErrorKind::CacheError).with_source(e));
+                },
+            };
+        }
+        match self.failed.take() {
+            Some(e) => {
+                Err(e)
+            },
+            None => {
+                Ok(())
+            },
+        }
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        match self.conn.query_row("SELECT 1 FROM entry WHERE id = ?1", params![id], |_r| Ok(())).optional() {
+            Ok(v) => {
+                v.is_some()
+            },
+            Err(_e) => {
+                false
+            },
+        }
+    }
+
+    fn modified(&self, id: &str) -> Option<FixedDateTime> {
+        let v: Option<String> = self.conn.query_row("SELECT modified FROM entry WHERE id = ?1", params![id], |r| r.get(0)).optional().ok().flatten();
+        v.and_then(|s| DateTime::parse_from_rfc3339(s.as_str()).ok())
+    }
+
+    fn seen(&self, id: &str) -> Option<FixedDateTime> {
+        let v: Option<String> = self.conn.query_row("SELECT COALESCE(seen, modified) FROM entry WHERE id = ?1", params![id], |r| r.get(0)).optional().ok().flatten();
+        v.and_then(|s| DateTime::parse_from_rfc3339(s.as_str()).ok())
+    }
+}
+
+impl Drop for SqliteCache {
+    fn drop(&mut self) {
+        match self.sync() {
+            Ok(_) => {},
+            Err(e) => {
+                error!("{}", e);
+            },
+        };
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::PathBuf;
+use std::fs::File;
+use std::fs::create_dir_all;
+use std::fs::read_to_string;
+use std::fs::rename;
+use std::fs::remove_file;
+use std::process;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::io::Write;
+
+use atom_syndication::Feed;
+use log::debug;
+use log::error;
+
+use super::FeedState;
+use crate::Error;
+use crate::ErrorKind;
+use crate::digest_hex;
+
+static STATE_FILE: &str = "state";
+
+
+/// Persists the `FeedState` of each source, together with a snapshot of the last feed
+/// retrieved from it.
+///
+/// The snapshot lets an unchanged source contribute its entries without being retrieved and
+/// parsed again.
+///
+/// State is kept in a tab separated file in the given directory, one source per line, with
+/// the snapshots alongside it named by the digest of the source uri. Tabs, line breaks and
+/// backslashes in the values are escaped with a backslash.
+pub struct StateStore {
+    dir: PathBuf,
+    states: HashMap<String, FeedState>,
+}
+
+/// escape the characters that separate fields and lines in the state file.
+fn escape(v: &str) -> String {
+    let mut r = String::with_capacity(v.len());
+
+    for c in v.chars() {
+        match c {
+            '\\' => {
+                r.push_str("\\\\");
+            },
+            '\t' => {
+                r.push_str("\\t");
+            },
+            '\n' => {
+                r.push_str("\\n");
+            },
+            '\r' => {
+                r.push_str("\\r");
+            },
+            _ => {
+                r.push(c);
+            },
+        };
+    }
+    r
+}
+
+fn unescape(v: &str) -> String {
+    let mut r = String::with_capacity(v.len());
+    let mut chars = v.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            r.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => {
+                r.push('\t');
+            },
+            Some('n') => {
+                r.push('\n');
+            },
+            Some('r') => {
+                r.push('\r');
+            },
+            Some(vv) => {
+                r.push(vv);
+            },
+            None => {
+                r.push(c);
+            },
+        };
+    }
+    r
+}
+
+fn field(v: &str) -> Option<String> {
+    if v.is_empty() {
+        return None;
+    }
+    Some(unescape(v))
+}
+
+fn unfield(v: &Option<String>) -> String {
+    match v {
+        Some(vv) => {
+            escape(vv)
+        },
+        None => {
+            String::new()
+        },
+    }
+}
+
+impl StateStore {
+    /// Open the state store in the given directory, loading any previously saved state.
+    pub fn new(dir: PathBuf) -> Result<StateStore, Error> {
+        let mut o = StateStore{
+            dir: dir,
+            states: HashMap::new(),
+        };
+
+        match create_dir_all(&o.dir) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_uri(&o.dir.to_string_lossy()).with_source(e));
+            },
+        };
+
+        let s = match read_to_string(o.dir.join(STATE_FILE)) {
+            Ok(v) => v,
+            Err(_e) => {
+                debug!("no previous state in {:?}", o.dir);
+                return Ok(o);
+            },
+        };
+
+        for l in s.lines() {
+            let v: Vec<&str> = l.split('\t').collect();
+            if v.len() != 5 {
+                error!("skipping invalid state line: {}", l);
+                continue;
+            }
+            o.states.insert(unescape(v[0]), FeedState{
+                etag: field(v[1]),
+                last_modified: field(v[2]),
+                mtime: v[3].parse().ok(),
+                digest: field(v[4]),
+            });
+        }
+        Ok(o)
+    }
+
+    fn snapshot_path(&self, uri: &str) -> PathBuf {
+        self.dir.join(format!("{}.xml", digest_hex(uri.as_bytes())))
+    }
+
+    /// The recorded state for the source, empty if it has not been seen before.
+    pub fn get(&self, uri: &str) -> FeedState {
+        match self.states.get(uri) {
+            Some(v) => {
+                v.clone()
+            },
+            None => {
+                FeedState::default()
+            },
+        }
+    }
+
+    /// Record the state of a source along with the feed it produced.
+    ///
+    /// The snapshot is written to a temporary file first, and then moved in place of the
+    /// previous one.
+    pub fn set(&mut self, uri: &str, state: FeedState, feed: &Feed) -> Result<(), Error> {
+        let f: File;
+        let fp = self.snapshot_path(uri);
+        let fp_tmp = fp.with_extension(format!("{}.tmp", process::id()));
+
+        match File::create(&fp_tmp) {
+            Ok(v) => {
+                f = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_uri(uri).with_source(e));
+            },
+        };
+        match feed.write_to(f) {
+            Ok(_) => {},
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                return Err(Error::new(ErrorKind::CacheError).with_uri(uri).with_source(e));
+            },
+        };
+        match rename(&fp_tmp, &fp) {
+            Ok(_) => {},
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                return Err(Error::new(ErrorKind::CacheError).with_uri(uri).with_source(e));
+            },
+        };
+        self.states.insert(String::from(uri), state);
+        Ok(())
+    }
+
+    /// Record the refreshed state of a source that has not changed, keeping its snapshot.
+    pub fn update(&mut self, uri: &str, state: FeedState) {
+        self.states.insert(String::from(uri), state);
+    }
+
+    /// The feed last recorded for the source, if any.
+    pub fn feed(&self, uri: &str) -> Option<Feed> {
+        match File::open(self.snapshot_path(uri)) {
+            Ok(v) => {
+                Feed::read_from(BufReader::new(v)).ok()
+            },
+            Err(_e) => {
+                None
+            },
+        }
+    }
+
+    /// Write the state of all sources to disk.
+    pub fn save(&self) -> Result<(), Error> {
+        let mut f: File;
+        let fp = self.dir.join(STATE_FILE);
+        let fp_tmp = self.dir.join(format!("{}.{}.tmp", STATE_FILE, process::id()));
+
+        match File::create(&fp_tmp) {
+            Ok(v) => {
+                f = v;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::CacheError).with_uri(&fp_tmp.to_string_lossy()).with_source(e));
+            },
+        };
+
+        for (k, v) in self.states.iter() {
+            let mtime = match v.mtime {
+                Some(t) => t.to_string(),
+                None => String::new(),
+            };
+            match writeln!(f, "{}\t{}\t{}\t{}\t{}", escape(k), unfield(&v.etag), unfield(&v.last_modified), mtime, unfield(&v.digest)) {
+                Ok(_) => {},
+                Err(e) => {
+                    let _ = remove_file(&fp_tmp);
+                    return Err(Error::new(ErrorKind::CacheError).with_uri(&fp_tmp.to_string_lossy()).with_source(e));
+                },
+            };
+        }
+
+        match rename(&fp_tmp, &fp) {
+            Ok(_) => {
+                Ok(())
+            },
+            Err(e) => {
+                let _ = remove_file(&fp_tmp);
+                Err(Error::new(ErrorKind::CacheError).with_uri(&fp.to_string_lossy()).with_source(e))
+            },
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::io::Write;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::de::Error as DeError;
+use serde::Serialize;
+use log::debug;
+use log::info;
+use atom_syndication::Feed;
+use atom_syndication::Entry;
+use atom_syndication::Text;
+use atom_syndication::Content;
+use atom_syndication::Category;
+use atom_syndication::Link;
+use atom_syndication::Person;
+use atom_syndication::FixedDateTime;
+use chrono::Local;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::date::parse as parse_date;
+use crate::rss::DateFallback;
+use crate::rss::text_plain;
+use crate::rss::unwrap_xhtml;
+
+static JSONFEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+
+#[derive(Serialize, Deserialize, Default)]
+struct JsonAuthor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avatar: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct JsonAttachment {
+    url: String,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_in_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct JsonItem {
+    #[serde(default, deserialize_with = "deserialize_id")]
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonAuthor>,
+    /// Single author of JSON Feed 1.0, only read.
+    #[serde(default, skip_serializing)]
+    author: Option<JsonAuthor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<JsonAttachment>,
+}
+
+/// A JSON Feed document.
+#[derive(Serialize, Deserialize, Default)]
+struct JsonFeed {
+    version: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    home_page_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feed_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonAuthor>,
+    #[serde(default, skip_serializing)]
+    author: Option<JsonAuthor>,
+    items: Vec<JsonItem>,
+}
+
+fn link(href: &str, rel: &str) -> Link {
+    let mut o = Link::default();
+    o.set_href(href);
+    o.set_rel(rel);
+    o
+}
+
+/// authors of the item or feed; the 1.1 list if present, otherwise the 1.0 single author.
+fn to_persons(authors: Vec<JsonAuthor>, author: Option<JsonAuthor>) -> Vec<Person> {
+    let mut r: Vec<Person>;
+
+    r = Vec::new();
+    for v in authors.into_iter().chain(author.into_iter()) {
+        match v.name {
+            Some(name) => {
+                r.push(Person{
+                    name: name,
+                    email: None,
+                    uri: v.url,
+                });
+            },
+            None => {},
+        };
+    }
+    r
+}
+
+/// read an item id given as a string or, as some feeds do, as a number.
+fn deserialize_id<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(d)? {
+        serde_json::Value::String(v) => {
+            Ok(v)
+        },
+        serde_json::Value::Number(v) => {
+            Ok(v.to_string())
+        },
+        serde_json::Value::Null => {
+            Ok(String::new())
+        },
+        v => {
+            Err(D::Error::custom(format!("invalid item id: {}", v)))
+        },
+    }
+}
+
+/// coerce the json feed item into an atom entry
+///
+/// An item with neither published nor modified date gets the fallback date if one is given,
+/// as with rss items.
+fn translate_item(ipt: JsonItem, fallback: Option<(FixedDateTime, DateFallback)>) -> Result<Entry, Error> {
+    let mut opt = Entry::default();
+
+    match ipt.url {
+        Some(v) => {
+            if ipt.id.is_empty() {
+                opt.set_id(v.clone());
+            }
+            opt.links.push(link(v.as_str(), "alternate"));
+        },
+        None => {},
+    };
+    if !ipt.id.is_empty() {
+        opt.set_id(ipt.id);
+    }
+    if opt.id.is_empty() {
+        return Err(Error::new(ErrorKind::IncompleteError).with_source("item has no id"));
+    }
+
+    match ipt.external_url {
+        Some(v) => {
+            opt.links.push(link(v.as_str(), "related"));
+        },
+        None => {},
+    };
+
+    match ipt.title {
+        Some(v) => {
+            opt.set_title(Text::plain(v));
+        },
+        None => {},
+    };
+
+    match (ipt.date_published, ipt.date_modified) {
+        (Some(published), Some(modified)) => {
+            opt.set_published(Some(parse_date(published.as_str())?));
+            opt.set_updated(parse_date(modified.as_str())?);
+        },
+        (Some(v), None) | (None, Some(v)) => {
+            let d = parse_date(v.as_str())?;
+            opt.set_published(Some(d.clone()));
+            opt.set_updated(d);
+        },
+        (None, None) => {
+            match fallback {
+                Some((v, kind)) => {
+                    debug!("item {} has no date, using fallback date", opt.id);
+                    kind.apply(&mut opt, v);
+                },
+                None => {
+                    return Err(Error::new(ErrorKind::IncompleteError).with_source("item has no date"));
+                },
+            };
+        },
+    };
+
+    match ipt.summary {
+        Some(v) => {
+            opt.set_summary(Some(Text::plain(v)));
+        },
+        None => {},
+    };
+
+    let mut r = Content::default();
+    match ipt.content_html {
+        Some(v) => {
+            r.set_content_type(Some(String::from("html")));
+            r.set_value(Some(v));
+            opt.set_content(Some(r));
+        },
+        None => {
+            match ipt.content_text {
+                Some(v) => {
+                    r.set_content_type(Some(String::from("text")));
+                    r.set_value(Some(v));
+                    opt.set_content(Some(r));
+                },
+                None => {
+                    if opt.summary.is_none() {
+                        return Err(Error::new(ErrorKind::IncompleteError).with_source("item has neither summary nor content"));
+                    }
+                },
+            };
+        },
+    };
+
+    opt.set_authors(to_persons(ipt.authors, ipt.author));
+
+    for v in ipt.tags {
+        let mut cat = Category::default();
+        cat.set_term(v);
+        opt.categories.push(cat);
+    }
+
+    for v in ipt.attachments {
+        let mut l = link(v.url.as_str(), "enclosure");
+        l.set_mime_type(Some(v.mime_type));
+        l.set_title(v.title);
+        l.set_length(v.size_in_bytes.map(|s| s.to_string()));
+        opt.links.push(l);
+    }
+
+    Ok(opt)
+}
+
+/// coerce the json feed into an atom feed
+///
+/// JSON Feed has no feed date, so the feed is dated by its most recently updated entry.
+fn translate(ipt: JsonFeed, allow_fail: bool, date_fallback: DateFallback) -> Result<Feed, Error> {
+    let mut entries: Vec<Entry>;
+    let mut opt = Feed::default();
+
+    opt.set_title(Text::plain(&ipt.title));
+    match &ipt.description {
+        Some(v) => {
+            opt.set_subtitle(Some(Text::plain(v)));
+        },
+        None => {},
+    };
+
+    match &ipt.home_page_url {
+        Some(v) => {
+            opt.links.push(link(v.as_str(), "alternate"));
+        },
+        None => {},
+    };
+    match &ipt.feed_url {
+        Some(v) => {
+            opt.links.push(link(v.as_str(), "self"));
+        },
+        None => {},
+    };
+    opt.set_id(ipt.feed_url.clone().or(ipt.home_page_url.clone()).unwrap_or(ipt.title.clone()));
+    opt.set_authors(to_persons(ipt.authors, ipt.author));
+
+    let fetched = Local::now().fixed_offset();
+    entries = vec!();
+    for (i, v) in ipt.items.into_iter().enumerate() {
+        let ident = v.id.clone();
+        let fallback = date_fallback.date(None, fetched, i);
+        match translate_item(v, fallback.map(|v| (v, date_fallback))) {
+            Ok(v) => {
+                entries.push(v);
+            },
+            Err(e) => {
+                if !allow_fail {
+                    return Err(e.with_entry(ident.as_str()));
+                }
+                info!("skipping item {}: {}", ident, e);
+            },
+        }
+    }
+
+    match entries.iter().map(|v| v.updated).max() {
+        Some(v) => {
+            opt.set_updated(v);
+        },
+        None => {},
+    };
+    opt.set_entries(entries);
+    Ok(opt)
+}
+
+/// true if the document looks like json rather than xml.
+pub fn is_json(b: &[u8]) -> bool {
+    match b.iter().find(|v| !v.is_ascii_whitespace()) {
+        Some(v) => {
+            *v == b'{'
+        },
+        None => {
+            false
+        },
+    }
+}
+
+/// parse a JSON Feed document already in memory.
+pub fn from_bytes(b: &[u8], allow_entry_fail: bool, date_fallback: DateFallback) -> Result<Feed, Error> {
+    let o: JsonFeed;
+
+    match serde_json::from_slice(b) {
+        Ok(v) => {
+            debug!("have json feed");
+            o = v;
+        },
+        Err(e) => {
+            return Err(Error::new(ErrorKind::ParseError).with_source(e));
+        },
+    };
+    translate(o, allow_entry_fail, date_fallback)
+}
+
+fn alternate_link(links: &[Link], rel: &str) -> Option<String> {
+    links.iter().find(|v| v.rel == rel).map(|v| v.href.clone())
+}
+
+fn from_persons(persons: &[Person]) -> Vec<JsonAuthor> {
+    persons.iter().map(|v| JsonAuthor{
+        name: Some(v.name.clone()),
+        url: v.uri.clone(),
+        avatar: None,
+    }).collect()
+}
+
+/// coerce the atom entry into a json feed item
+fn entry_to_item(ipt: &Entry) -> JsonItem {
+    let mut opt = JsonItem::default();
+
+    opt.id = ipt.id.clone();
+    opt.url = alternate_link(&ipt.links, "alternate");
+    opt.external_url = alternate_link(&ipt.links, "related");
+    opt.title = Some(text_plain(&ipt.title));
+    opt.summary = ipt.summary.as_ref().map(text_plain);
+
+    match &ipt.content {
+        Some(v) => {
+            match v.content_type.as_deref() {
+                Some("text") | Some("text/plain") => {
+                    opt.content_text = v.value.clone();
+                },
+                Some("xhtml") => {
+                    opt.content_html = v.value.as_deref().map(|vv| String::from(unwrap_xhtml(vv)));
+                },
+                _ => {
+                    opt.content_html = v.value.clone();
+                },
+            };
+        },
+        None => {},
+    };
+
+    opt.date_published = ipt.published.map(|v| v.to_rfc3339());
+    opt.date_modified = Some(ipt.updated.to_rfc3339());
+    opt.authors = from_persons(&ipt.authors);
+    opt.tags = ipt.categories.iter().map(|v| v.term.clone()).collect();
+
+    for v in ipt.links.iter().filter(|v| v.rel == "enclosure") {
+        opt.attachments.push(JsonAttachment{
+            url: v.href.clone(),
+            mime_type: v.mime_type.clone().unwrap_or(String::from("application/octet-stream")),
+            title: v.title.clone(),
+            size_in_bytes: v.length.as_ref().and_then(|s| s.parse().ok()),
+        });
+    }
+
+    opt
+}
+
+/// Write the atom feed as a JSON Feed document.
+pub(crate) fn write_to(ipt: &Feed, w: impl Write) -> Result<(), Error> {
+    let mut opt = JsonFeed::default();
+
+    opt.version = String::from(JSONFEED_VERSION);
+    opt.title = text_plain(&ipt.title);
+    opt.home_page_url = alternate_link(&ipt.links, "alternate");
+    opt.feed_url = alternate_link(&ipt.links, "self");
+    opt.description = ipt.subtitle.as_ref().map(text_plain);
+    opt.authors = from_persons(&ipt.authors);
+    opt.items = ipt.entries.iter().map(|v| entry_to_item(v)).collect();
+
+    match serde_json::to_writer(w, &opt) {
+        Ok(_) => {
+            Ok(())
+        },
+        Err(e) => {
+            Err(Error::new(ErrorKind::WriteError).with_source(e))
+        },
+    }
+}
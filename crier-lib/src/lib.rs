@@ -11,6 +11,7 @@ use uuid::Uuid;
 
 use rs_sha512::Sha512Hasher;
 use chrono::Local;
+use chrono::TimeDelta;
 use atom_syndication::Feed as Feed;
 use atom_syndication::Entry as Entry;
 use atom_syndication::TextType as OutTextType;
@@ -21,27 +22,71 @@ use atom_syndication::Category as OutCategory;
 use atom_syndication::FixedDateTime;
 use atom_syndication::Person;
 use atom_syndication::Generator;
+use atom_syndication::Source;
 use itertools::Itertools;
+use ::rss::Channel;
 
 
 pub mod io;
 pub mod mem;
+pub mod opml;
 
 mod meta;
-mod cache;
+pub mod cache;
 mod rss;
+mod json;
+mod rdf;
+mod date;
+#[cfg(feature = "sanitize")]
+pub mod sanitize;
+mod error;
+pub use error::Error;
+pub use error::ErrorKind;
 use meta::FeedMetadata;
-use mem::CacheWriter;
 use cache::Cache;
+pub use cache::CachedEntry;
+pub use rss::DateFallback;
+pub use rss::DATE_FALLBACK_SCHEME;
+use cache::retention::RetentionPolicy;
+use cache::retention::gc;
 
 static NAMESPACE_URL_CRIER: &[u8] = b"defalsify.org/src/crier";
 
-#[derive(Debug)]
-pub enum Error {
-    WriteError,
-    CacheError,
-    ParseError,
-    IncompleteError,
+/// uuid namespace for the v5 ids crier derives.
+pub(crate) fn namespace() -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, NAMESPACE_URL_CRIER)
+}
+
+/// hex encoded sha512 digest of the given bytes.
+pub(crate) fn digest_hex(v: &[u8]) -> String {
+    use rs_sha512::HasherContext;
+    let mut h = Sha512Hasher::default();
+    h.write(v);
+    format!("{:02x}", HasherContext::finish(&mut h))
+}
+
+/// Direction entries are output in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequencerOrder {
+    OldestFirst,
+    NewestFirst,
+}
+
+/// Date entries are ordered by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequencerKey {
+    /// The published date, or the updated date if the entry has none.
+    Published,
+    Updated,
+    /// The time the entry was first added to the sequencer.
+    Seen,
+}
+
+/// The dates an entry can be sequenced by.
+struct SequencerDates {
+    published: FixedDateTime,
+    updated: FixedDateTime,
+    seen: FixedDateTime,
 }
 
 pub struct Sequencer<'a> {
@@ -50,29 +95,43 @@ pub struct Sequencer<'a> {
     item_keys: Vec<u64>,
     crsr: usize,
     limit: usize,
-    default_cache: CacheWriter, //HashMap<String, Vec<u8>>,
     cache: Option<&'a mut dyn Cache>,
     guuid: Uuid,
+    item_dates: HashMap<u64, SequencerDates>,
+    order: SequencerOrder,
+    key: SequencerKey,
+    max_entries: Option<usize>,
+    since: Option<FixedDateTime>,
+    until: Option<FixedDateTime>,
+    max_age: Option<TimeDelta>,
+    provenance: bool,
 }
 
 pub struct SequencerEntry {
     pub digest: u64,
     entry: Entry,
-    out: Vec<u8>,
+    source: String,
 }
 
 impl<'a> Sequencer<'a> {
     pub fn new(guuid_value: Vec<u8>) -> Sequencer<'a> {
-        let namespace_crier = Uuid::new_v5(&Uuid::NAMESPACE_URL, NAMESPACE_URL_CRIER);
+        let namespace_crier = namespace();
         let mut o = Sequencer {
             metadata: FeedMetadata::default(),
             items: HashMap::new(),
             crsr: 0,
             limit: 0,
             item_keys: Vec::new(),
-            default_cache: CacheWriter::new(), //HashMap::new(),
             cache: None,
             guuid: Uuid::new_v5(&namespace_crier, guuid_value.as_ref()),
+            item_dates: HashMap::new(),
+            order: SequencerOrder::OldestFirst,
+            key: SequencerKey::Published,
+            max_entries: None,
+            since: None,
+            until: None,
+            max_age: None,
+            provenance: false,
         };
 
         #[cfg(test)]
@@ -98,49 +157,271 @@ impl<'a> Sequencer<'a> {
         self.metadata.set_title(String::from(title))
     }
 
+    pub fn set_order(&mut self, order: SequencerOrder) {
+        self.order = order;
+    }
+
+    /// Select the date entries are ordered by. The output bounds also apply to this date.
+    pub fn set_key(&mut self, key: SequencerKey) {
+        self.key = key;
+    }
+
+    fn item_date(&self, k: &u64) -> &FixedDateTime {
+        let v = &self.item_dates[k];
+        match self.key {
+            SequencerKey::Published => {
+                &v.published
+            },
+            SequencerKey::Updated => {
+                &v.updated
+            },
+            SequencerKey::Seen => {
+                &v.seen
+            },
+        }
+    }
+
+    /// Only output the given number of most recent entries.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = Some(max_entries);
+    }
+
+    /// Only output entries dated at or after the given time.
+    pub fn set_since(&mut self, since: FixedDateTime) {
+        self.since = Some(since);
+    }
+
+    /// Only output entries dated at or before the given time.
+    pub fn set_until(&mut self, until: FixedDateTime) {
+        self.until = Some(until);
+    }
+
+    /// Only output entries no older than the given age at the time of output.
+    pub fn set_max_age(&mut self, max_age: TimeDelta) {
+        self.max_age = Some(max_age);
+    }
+
+    /// Record the originating feed of entries added with `add_from` in their `atom:source`
+    /// element. Entries that already carry a source keep it.
+    pub fn set_provenance(&mut self, provenance: bool) {
+        self.provenance = provenance;
+    }
+
+    /// true if an entry with the given date falls within the configured output window.
+    fn in_window(&self, date: &FixedDateTime) -> bool {
+        match &self.since {
+            Some(v) => {
+                if date < v {
+                    return false;
+                }
+            },
+            None => {},
+        };
+        match &self.until {
+            Some(v) => {
+                if date > v {
+                    return false;
+                }
+            },
+            None => {},
+        };
+        match &self.max_age {
+            Some(v) => {
+                if date.to_utc() < Local::now().to_utc() - *v {
+                    return false;
+                }
+            },
+            None => {},
+        };
+        true
+    }
+
+    /// Add an entry that does not belong to any particular source.
+    ///
+    /// See `add_with_source`.
     pub fn add(&mut self, entry: Entry) -> bool {
-        let w: &mut dyn Write;
-        let mut id: String;
+        self.add_with_source(entry, "")
+    }
+
+    /// Add an entry, identified by its id within the given source.
+    ///
+    /// If an entry with the same identity has already been added, it is only replaced if the new
+    /// revision has a later updated date, or the same updated date but different content. A new
+    /// revision dated by a `DateFallback` keeps the date of the previous one.
+    ///
+    /// Returns true if the entry was added or replaced the previous revision.
+    pub fn add_with_source(&mut self, entry: Entry, source: &str) -> bool {
+        let o = SequencerEntry::new(entry, source);
+        self.insert(o, Local::now().fixed_offset(), true)
+    }
+
+    /// Add an entry previously written to the cache, without writing it again.
+    ///
+    /// `seen` is used as the time the entry was first added, unless the sequencer already knows
+    /// an earlier one.
+    pub fn restore(&mut self, cached: CachedEntry) -> bool {
+        let o = SequencerEntry::new(cached.entry, cached.source.as_str());
+        self.insert(o, cached.seen, false)
+    }
+
+    /// Add all entries held by the cache, as with `restore`.
+    ///
+    /// Returns the number of entries restored. Cache items that cannot be read back as entries
+    /// are skipped.
+    pub fn restore_cache(&mut self) -> Result<usize, Error> {
+        let mut r: Vec<CachedEntry>;
+        let mut c: usize;
+
+        r = Vec::new();
+        match &self.cache {
+            Some(v) => {
+                for k in v.list()? {
+                    match CachedEntry::from_cache(&**v, k.as_str()) {
+                        Ok(Some(e)) => {
+                            r.push(e);
+                        },
+                        Ok(None) => {},
+                        Err(e) => {
+                            error!("skipping cached item: {}", e);
+                        },
+                    };
+                }
+            },
+            None => {},
+        };
+
+        c = 0;
+        for v in r {
+            if self.restore(v) {
+                c += 1;
+            }
+        }
+        Ok(c)
+    }
+
+    /// Enforce the retention policies on the cache, see `cache::retention::gc`.
+    ///
+    /// Evicted entries are also removed from the sequencer. Returns the keys of the evicted
+    /// entries.
+    pub fn gc(&mut self, policies: &[&dyn RetentionPolicy]) -> Result<Vec<String>, Error> {
+        let r: Vec<String>;
 
-        id = entry.id.to_string();
         match &mut self.cache {
             Some(v) => {
-                w = v.open(id);
+                r = gc(&mut **v, policies)?;
             },
             None => {
-                w = &mut self.default_cache;
+                return Ok(Vec::new());
             },
+        };
+        for k in r.iter() {
+            let digest = key_digest(k.as_str());
+            self.items.remove(&digest);
+            self.item_dates.remove(&digest);
         }
+        Ok(r)
+    }
 
-        id = entry.id.to_string();
-        let o = SequencerEntry::new(entry, w);
-        if self.items.contains_key(&o.digest) {
-            return false;
+    /// Write pending changes of the cache, see `Cache::sync`.
+    pub fn sync(&mut self) -> Result<(), Error> {
+        match &mut self.cache {
+            Some(v) => {
+                v.sync()
+            },
+            None => {
+                Ok(())
+            },
         }
-        self.items.insert(o.digest, o.into());
+    }
+
+    fn insert(&mut self, mut o: SequencerEntry, mut seen: FixedDateTime, write: bool) -> bool {
+        let w: &mut dyn Write;
+        let key: String;
+        let b: Vec<u8>;
+
+        match self.item_dates.get(&o.digest) {
+            Some(v) => {
+                // a fallback date is made up anew on every read, so keep the one first given.
+                if o.has_fallback_date() {
+                    o.entry.set_published(Some(v.published));
+                    o.entry.set_updated(v.updated);
+                }
+                if o.entry.updated < v.updated {
+                    return false;
+                }
+                if v.seen < seen {
+                    seen = v.seen.clone();
+                }
+            },
+            None => {},
+        };
+
+        key = o.key();
+        let digest = o.digest;
+        let dates = SequencerDates{
+            published: o.date(),
+            updated: o.entry.updated.clone(),
+            seen: seen,
+        };
+        b = o.into();
+        match self.items.get(&digest) {
+            Some(v) => {
+                if *v == b {
+                    return false;
+                }
+            },
+            None => {},
+        };
+        self.item_dates.insert(digest, dates);
+
         match &mut self.cache {
             Some(v) => {
-                v.close(id);
+                if write {
+                    w = v.open(key.clone());
+                    match w.write_all(b.as_slice()) {
+                        Ok(_) => {},
+                        Err(e) => {
+                            error!("cache write failed for {}: {:?}", key, e);
+                        },
+                    };
+                    v.close(key);
+                }
             },
             None => {
             },
         }
+        self.items.insert(digest, b);
         return true;
     }
 
+    /// Add all entries of the feed, without identifying them by any particular source.
+    ///
+    /// See `add_from_source`.
     pub fn add_from(&mut self, feed: Feed) -> i64 {
+        self.add_from_source(feed, "")
+    }
+
+    /// Add all entries of the feed retrieved from the given source uri, as with
+    /// `add_with_source`.
+    ///
+    /// Returns the number of entries in the feed.
+    pub fn add_from_source(&mut self, feed: Feed, source: &str) -> i64 {
         let mut c: i64;
 
         c = 0;
         for v in feed.entries.iter() {
-            self.add(v.clone());
+            let mut entry = v.clone();
+            if self.provenance && entry.source.is_none() {
+                entry.source = Some(feed_source(&feed));
+            }
+            self.add_with_source(entry, source);
             c += 1;
         }
         c
     }
 
-    pub fn write_to(&mut self, w: impl Write) -> Result<usize, Error> {
-        let mut r: usize;
+    /// Assemble the aggregated feed from the sequenced entries.
+    pub fn to_feed(&mut self) -> Result<Feed, Error> {
         let mut feed = Feed::default();
         let mut entry: Entry;
         let mut entries: Vec<Entry>;
@@ -156,34 +437,36 @@ impl<'a> Sequencer<'a> {
         };
         feed.set_generator(g);
 
-        match self.metadata.apply(&mut feed) {
-            Err(_v) => {
-                return Err(Error::WriteError);
-            },
-            Ok(_) => {
-            },
-        }
+        self.metadata.apply(&mut feed)?;
 
         entries = Vec::new();
-        r = 0;
         for v in self {
             b = std::str::from_utf8(v.as_slice()).unwrap();
             match Entry::from_str(b) {
                 Err(e) => {
                     error!("fromstrerr {:?}", e);
-                    return Err(Error::CacheError);
+                    return Err(Error::new(ErrorKind::CacheError).with_source(e));
                 },
                 Ok(o) => {
                     entries.push(o);
                 },
             }
-            r += 1;
         }
         feed.set_entries(entries);
 
+        Ok(feed)
+    }
+
+    pub fn write_to(&mut self, w: impl Write) -> Result<usize, Error> {
+        let r: usize;
+        let feed: Feed;
+
+        feed = self.to_feed()?;
+        r = feed.entries.len();
+
         match feed.write_to(w) {
-            Err(_v) => {
-                return Err(Error::WriteError);
+            Err(e) => {
+                return Err(Error::new(ErrorKind::WriteError).with_source(e));
             },
             Ok(_) => {
             },
@@ -191,6 +474,63 @@ impl<'a> Sequencer<'a> {
 
         Ok(r)
     }
+
+    /// The sequenced entries as an RSS 2.0 channel, see `to_feed`.
+    pub fn to_channel(&mut self) -> Result<Channel, Error> {
+        let feed: Feed;
+
+        feed = self.to_feed()?;
+        Ok(crate::rss::to_channel(&feed))
+    }
+
+    /// Write the sequenced entries as an RSS 2.0 document, returning the number of entries.
+    pub fn write_rss_to(&mut self, w: impl Write) -> Result<usize, Error> {
+        let r: usize;
+        let channel: Channel;
+
+        channel = self.to_channel()?;
+        r = channel.items.len();
+
+        match channel.write_to(w) {
+            Err(e) => {
+                return Err(Error::new(ErrorKind::WriteError).with_source(e));
+            },
+            Ok(_) => {
+            },
+        }
+
+        Ok(r)
+    }
+
+    /// Write the sequenced entries as a JSON Feed document, returning the number of entries.
+    pub fn write_json_to(&mut self, w: impl Write) -> Result<usize, Error> {
+        let feed: Feed;
+
+        feed = self.to_feed()?;
+        crate::json::write_to(&feed, w)?;
+        Ok(feed.entries.len())
+    }
+}
+
+/// The digest identifying the entry with the given key in the sequencer.
+fn key_digest(key: &str) -> u64 {
+    let mut h = Sha512Hasher::default();
+    h.write(key.as_bytes());
+    h.finish()
+}
+
+/// The `atom:source` element describing the given feed.
+fn feed_source(feed: &Feed) -> Source {
+    let mut o = Source::default();
+    o.id = feed.id.clone();
+    o.title = feed.title.clone();
+    o.links = feed.links.clone();
+    o.updated = feed.updated;
+    o.authors = feed.authors.clone();
+    o.rights = feed.rights.clone();
+    o.logo = feed.logo.clone();
+    o.icon = feed.icon.clone();
+    o
 }
 
 impl<'a> Iterator for Sequencer<'a> {
@@ -201,10 +541,25 @@ impl<'a> Iterator for Sequencer<'a> {
 
         if self.limit == 0 {
             self.item_keys = Vec::new();
-            for k in  self.items.keys().sorted() {
+            for k in  self.items.keys().sorted_by_key(|k| (self.item_date(k), *k)) {
+                if !self.in_window(self.item_date(k)) {
+                    continue;
+                }
                 self.item_keys.push(k.clone());
                 self.limit += 1;
             }
+            match self.max_entries {
+                Some(v) => {
+                    if self.limit > v {
+                        self.item_keys.drain(..self.limit - v);
+                        self.limit = v;
+                    }
+                },
+                None => {},
+            };
+            if self.order == SequencerOrder::NewestFirst {
+                self.item_keys.reverse();
+            }
         }
 
         if self.limit == 0 {
@@ -224,39 +579,39 @@ impl<'a> Iterator for Sequencer<'a> {
 }
 
 impl SequencerEntry {
-    pub fn new(entry: Entry, exporter: &mut dyn Write) -> SequencerEntry {
-        let mut have_date: bool;
-        let mut id_part: u32;
+    pub fn new(entry: Entry, source: &str) -> SequencerEntry {
         let mut o = SequencerEntry {
             entry: entry,
+            source: String::from(source),
             digest: 0,
-            out: Vec::new(),
         };
 
-        have_date = false;
-        match &o.entry.published {
+        o.digest = key_digest(o.key().as_str());
+        o
+    }
+
+    /// the identity of the entry, which is its id qualified by the source if any.
+    ///
+    /// Entries are cached by this key, see `cache::source_key`.
+    pub fn key(&self) -> String {
+        cache::source_key(self.source.as_str(), self.entry.id.as_str())
+    }
+
+    /// the date the entry is sequenced by; published if available, otherwise updated.
+    pub fn date(&self) -> FixedDateTime {
+        match &self.entry.published {
             Some(v) => {
-                id_part = v.timestamp() as u32;
-                o.digest = id_part as u64;
-                o.digest <<= 32;
-                have_date = true;
+                v.clone()
             },
             None => {
+                self.entry.updated.clone()
             },
         }
+    }
 
-        if !have_date {
-            id_part = o.entry.updated.timestamp() as u32;
-            o.digest = id_part as u64;
-            o.digest <<= 32;
-            have_date = true;
-        }
-        
-        let mut h = Sha512Hasher::default();
-        o.hash(&mut h);
-        id_part = h.finish() as u32;
-        o.digest += id_part as u64;
-        o
+    /// true if the date of the entry was not given by its source, see `DateFallback`.
+    fn has_fallback_date(&self) -> bool {
+        self.entry.categories.iter().any(|v| v.scheme.as_deref() == Some(DATE_FALLBACK_SCHEME))
     }
 
     /// TODO: get size heuristics from already written values (either that or replace underlying
@@ -287,7 +642,7 @@ impl Into<Vec<u8>> for SequencerEntry {
 
 impl Hash for SequencerEntry {
     fn hash<H: Hasher>(&self, h: &mut H) {
-            h.write(self.entry.id.as_bytes());
+            h.write(self.key().as_bytes());
     }
 }
 
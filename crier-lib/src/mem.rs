@@ -1,12 +1,18 @@
 use std::collections::HashMap;
 use std::io::Write;
-use std::io::Error;
+use std::io;
+
+use atom_syndication::FixedDateTime;
+use chrono::Local;
 
 use crate::cache::Cache;
+use crate::Error;
 
 
 pub struct CacheWriter {
     data: Vec<u8>,
+    modified: FixedDateTime,
+    seen: FixedDateTime,
 }
 
 pub struct MemCache {
@@ -14,12 +20,12 @@ pub struct MemCache {
 }
 
 impl Write for CacheWriter {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
         self.data.extend_from_slice(buf);
-        Ok(self.data.len())
+        Ok(buf.len())
     }
 
-    fn flush(&mut self) -> Result<(), Error> {
+    fn flush(&mut self) -> Result<(), io::Error> {
         Ok(())
     }
 }
@@ -27,8 +33,11 @@ impl Write for CacheWriter {
 
 impl CacheWriter {
     pub fn new() -> CacheWriter {
+        let now = Local::now().fixed_offset();
         CacheWriter{
             data: Vec::new(),
+            modified: now,
+            seen: now,
         }
     }
 }
@@ -42,16 +51,52 @@ impl MemCache {
 }
 
 impl Cache for MemCache {
+    /// Any previously cached content for the id is discarded.
     fn open(&mut self, id: String) -> &mut dyn Write {
-        let w: CacheWriter;
-        if !self.files.contains_key(&id) {
-            w = CacheWriter::new();
-            self.files.insert(id.clone(), w);
-        }
+        let mut w: CacheWriter;
+        w = CacheWriter::new();
+        match self.files.get(&id) {
+            Some(v) => {
+                w.seen = v.seen;
+            },
+            None => {},
+        };
+        self.files.insert(id.clone(), w);
         self.files.get_mut(&id).unwrap()
     }
 
     fn close(&mut self, _id: String) -> usize {
         return 0;
     }
+
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.files.get(id) {
+            Some(v) => {
+                Ok(Some(v.data.clone()))
+            },
+            None => {
+                Ok(None)
+            },
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>, Error> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn remove(&mut self, id: &str) -> Result<bool, Error> {
+        Ok(self.files.remove(id).is_some())
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        self.files.contains_key(id)
+    }
+
+    fn modified(&self, id: &str) -> Option<FixedDateTime> {
+        self.files.get(id).map(|v| v.modified.clone())
+    }
+
+    fn seen(&self, id: &str) -> Option<FixedDateTime> {
+        self.files.get(id).map(|v| v.seen.clone())
+    }
 }
@@ -2,10 +2,8 @@ use atom_syndication::Person;
 use atom_syndication::Feed;
 use uuid::Uuid;
 
-#[derive(Debug)]
-pub enum Error {
-    IncompleteFeedMetadata,
-}
+use crate::Error;
+use crate::ErrorKind;
 
 
 pub struct FeedMetadata {
@@ -56,7 +54,7 @@ impl FeedMetadata {
 
     pub fn apply(&self, feed: &mut Feed) -> Result<(), Error> {
         if !self.check_complete() {
-            return Err(Error::IncompleteFeedMetadata);
+            return Err(Error::new(ErrorKind::IncompleteError).with_source("feed title and author must be set"));
         }
         let mut persons = Vec::<Person>::new();
         persons.push(self.author.clone());
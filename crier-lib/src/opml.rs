@@ -0,0 +1,222 @@
+use std::fs::read;
+use std::io::Write;
+
+use quick_xml::Reader;
+use quick_xml::Writer;
+use quick_xml::events::Event;
+use quick_xml::events::BytesStart;
+use quick_xml::events::BytesEnd;
+use quick_xml::events::BytesDecl;
+use quick_xml::events::BytesText;
+
+use crate::Error;
+use crate::ErrorKind;
+
+
+/// A feed subscription listed in an OPML document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpmlSource {
+    /// Location of the feed, from the `xmlUrl` attribute.
+    pub url: String,
+    pub title: Option<String>,
+    /// Location of the website of the feed, from the `htmlUrl` attribute.
+    pub html_url: Option<String>,
+    /// Titles of the enclosing outlines, outermost first.
+    pub groups: Vec<String>,
+}
+
+impl OpmlSource {
+    pub fn new(url: &str) -> OpmlSource {
+        let mut o = OpmlSource::default();
+        o.url = String::from(url);
+        o
+    }
+}
+
+struct Outline {
+    text: Option<String>,
+    title: Option<String>,
+    xml_url: Option<String>,
+    html_url: Option<String>,
+}
+
+fn outline(reader: &Reader<&[u8]>, e: &BytesStart) -> Result<Outline, Error> {
+    let mut o = Outline{
+        text: None,
+        title: None,
+        xml_url: None,
+        html_url: None,
+    };
+
+    for v in e.attributes() {
+        let v = match v {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_source(e));
+            },
+        };
+        let s = match v.decode_and_unescape_value(reader) {
+            Ok(s) => s.into_owned(),
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_source(e));
+            },
+        };
+        match v.key.as_ref().to_ascii_lowercase().as_slice() {
+            b"text" => o.text = Some(s),
+            b"title" => o.title = Some(s),
+            b"xmlurl" => o.xml_url = Some(s),
+            b"htmlurl" => o.html_url = Some(s),
+            _ => {},
+        };
+    }
+    Ok(o)
+}
+
+/// Read the feed subscriptions of an OPML document.
+///
+/// Outlines with an `xmlUrl` attribute are feeds. Any other outline is a group, and the feeds
+/// nested in it are listed with the group title.
+pub fn from_bytes(b: &[u8]) -> Result<Vec<OpmlSource>, Error> {
+    let mut r: Vec<OpmlSource>;
+    let mut buf: Vec<u8>;
+    let mut groups: Vec<Option<String>>;
+    let mut reader = Reader::from_reader(b);
+
+    r = Vec::new();
+    buf = Vec::new();
+    groups = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                if e.local_name().as_ref() == b"outline" {
+                    let v = outline(&reader, &e)?;
+                    match v.xml_url {
+                        Some(url) => {
+                            r.push(OpmlSource{
+                                url: url,
+                                title: v.title.or(v.text),
+                                html_url: v.html_url,
+                                groups: groups.iter().flatten().cloned().collect(),
+                            });
+                            groups.push(None);
+                        },
+                        None => {
+                            groups.push(v.text.or(v.title));
+                        },
+                    };
+                }
+            },
+            Ok(Event::Empty(e)) => {
+                if e.local_name().as_ref() == b"outline" {
+                    let v = outline(&reader, &e)?;
+                    match v.xml_url {
+                        Some(url) => {
+                            r.push(OpmlSource{
+                                url: url,
+                                title: v.title.or(v.text),
+                                html_url: v.html_url,
+                                groups: groups.iter().flatten().cloned().collect(),
+                            });
+                        },
+                        None => {},
+                    };
+                }
+            },
+            Ok(Event::End(e)) => {
+                if e.local_name().as_ref() == b"outline" {
+                    groups.pop();
+                }
+            },
+            Ok(Event::Eof) => {
+                break;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_source(e));
+            },
+            _ => {},
+        };
+        buf.clear();
+    }
+    Ok(r)
+}
+
+/// Read the feed subscriptions of an OPML file, see `from_bytes`.
+pub fn from_file(fp: &str) -> Result<Vec<OpmlSource>, Error> {
+    match read(fp) {
+        Ok(v) => {
+            from_bytes(v.as_slice()).map_err(|e| e.with_uri(fp))
+        },
+        Err(e) => {
+            Err(Error::from(e).with_uri(fp))
+        },
+    }
+}
+
+fn write_event<W: Write>(w: &mut Writer<W>, e: Event) -> Result<(), Error> {
+    match w.write_event(e) {
+        Ok(_) => {
+            Ok(())
+        },
+        Err(e) => {
+            Err(Error::new(ErrorKind::WriteError).with_source(e))
+        },
+    }
+}
+
+/// write the sources at the given group depth, nesting those in deeper groups.
+fn write_outlines<W: Write>(w: &mut Writer<W>, sources: &[&OpmlSource], depth: usize) -> Result<(), Error> {
+    let mut done: Vec<&str>;
+
+    done = Vec::new();
+    for v in sources.iter() {
+        if v.groups.len() <= depth {
+            let mut e = BytesStart::new("outline");
+            let title = v.title.as_deref().unwrap_or(v.url.as_str());
+            e.push_attribute(("type", "rss"));
+            e.push_attribute(("text", title));
+            e.push_attribute(("title", title));
+            e.push_attribute(("xmlUrl", v.url.as_str()));
+            match &v.html_url {
+                Some(u) => {
+                    e.push_attribute(("htmlUrl", u.as_str()));
+                },
+                None => {},
+            };
+            write_event(w, Event::Empty(e))?;
+            continue;
+        }
+
+        let group = v.groups[depth].as_str();
+        if done.contains(&group) {
+            continue;
+        }
+        done.push(group);
+
+        let members: Vec<&OpmlSource> = sources.iter().filter(|s| s.groups.len() > depth && s.groups[depth] == group).cloned().collect();
+        let mut e = BytesStart::new("outline");
+        e.push_attribute(("text", group));
+        write_event(w, Event::Start(e))?;
+        write_outlines(w, members.as_slice(), depth + 1)?;
+        write_event(w, Event::End(BytesEnd::new("outline")))?;
+    }
+    Ok(())
+}
+
+/// Write the sources as an OPML 2.0 document with the given title, nested by their groups.
+pub fn write_to(title: &str, sources: &[OpmlSource], w: impl Write) -> Result<(), Error> {
+    let mut writer = Writer::new_with_indent(w, b' ', 2);
+    let v: Vec<&OpmlSource> = sources.iter().collect();
+
+    write_event(&mut writer, Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    write_event(&mut writer, Event::Start(BytesStart::new("opml").with_attributes([("version", "2.0")])))?;
+    write_event(&mut writer, Event::Start(BytesStart::new("head")))?;
+    write_event(&mut writer, Event::Start(BytesStart::new("title")))?;
+    write_event(&mut writer, Event::Text(BytesText::new(title)))?;
+    write_event(&mut writer, Event::End(BytesEnd::new("title")))?;
+    write_event(&mut writer, Event::End(BytesEnd::new("head")))?;
+    write_event(&mut writer, Event::Start(BytesStart::new("body")))?;
+    write_outlines(&mut writer, v.as_slice(), 0)?;
+    write_event(&mut writer, Event::End(BytesEnd::new("body")))?;
+    write_event(&mut writer, Event::End(BytesEnd::new("opml")))?;
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use quick_xml::NsReader;
+use quick_xml::events::Event;
+use quick_xml::events::BytesStart;
+use quick_xml::name::Namespace;
+use quick_xml::name::ResolveResult;
+use rss::Channel;
+use rss::Item;
+use rss::Guid;
+use rss::extension::dublincore::DublinCoreExtension;
+use atom_syndication::Feed;
+use log::debug;
+
+use crate::Error;
+use crate::ErrorKind;
+use crate::rss::translate;
+use crate::rss::DateFallback;
+
+static NS_RDF: &[u8] = b"http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+static NS_RSS1: &[u8] = b"http://purl.org/rss/1.0/";
+static NS_DC: &[u8] = b"http://purl.org/dc/elements/1.1/";
+static NS_CONTENT: &[u8] = b"http://purl.org/rss/1.0/modules/content/";
+
+
+/// The element or attribute namespace, if bound.
+fn ns<'a>(r: &'a ResolveResult) -> &'a [u8] {
+    match r {
+        ResolveResult::Bound(Namespace(v)) => {
+            v
+        },
+        _ => {
+            b""
+        },
+    }
+}
+
+/// The value of the rdf:about attribute of the element, if any.
+fn about(reader: &NsReader<&[u8]>, e: &BytesStart) -> Option<String> {
+    for v in e.attributes().flatten() {
+        let (r, name) = reader.resolve_attribute(v.key);
+        if ns(&r) == NS_RDF && name.as_ref() == b"about" {
+            return v.decode_and_unescape_value(reader).ok().map(|s| s.into_owned());
+        }
+    }
+    None
+}
+
+/// A new item, with the rdf:about of the element as permalink guid if it has one.
+fn rdf_item(reader: &NsReader<&[u8]>, e: &BytesStart) -> Item {
+    let mut o = Item::default();
+
+    match about(reader, e) {
+        Some(s) => {
+            let mut guid = Guid::default();
+            guid.set_value(s);
+            guid.set_permalink(true);
+            o.set_guid(Some(guid));
+        },
+        None => {},
+    };
+    o
+}
+
+/// Add the Dublin Core element to the extension. Returns false if it is not one that is used.
+fn add_dc(dc: &mut DublinCoreExtension, name: &[u8], v: String) -> bool {
+    match name {
+        b"date" => dc.dates.push(v),
+        b"creator" => dc.creators.push(v),
+        b"contributor" => dc.contributors.push(v),
+        b"subject" => dc.subjects.push(v),
+        b"rights" => dc.rights.push(v),
+        b"publisher" => dc.publishers.push(v),
+        b"language" => dc.languages.push(v),
+        b"title" => dc.titles.push(v),
+        b"description" => dc.descriptions.push(v),
+        b"identifier" => dc.identifiers.push(v),
+        _ => {
+            return false;
+        },
+    };
+    true
+}
+
+/// true if the document is an RSS 1.0 document, that is, its root element is rdf:RDF.
+pub fn is_rdf(b: &[u8]) -> bool {
+    let mut buf: Vec<u8>;
+    let mut reader = NsReader::from_reader(b);
+
+    buf = Vec::new();
+    loop {
+        match reader.read_resolved_event_into(&mut buf) {
+            Ok((r, Event::Start(e))) | Ok((r, Event::Empty(e))) => {
+                return ns(&r) == NS_RDF && e.local_name().as_ref() == b"RDF";
+            },
+            Ok((_, Event::Eof)) | Err(_) => {
+                return false;
+            },
+            _ => {},
+        };
+        buf.clear();
+    }
+}
+
+/// parse an RSS 1.0 document already in memory.
+///
+/// The rdf:about of the channel and items become the ids of the feed and entries, and Dublin
+/// Core metadata is mapped to the corresponding atom fields.
+pub fn from_bytes(b: &[u8], uri: &str, allow_entry_fail: bool, date_fallback: DateFallback) -> Result<Feed, Error> {
+    let mut o: Feed;
+    let mut buf: Vec<u8>;
+    let mut text: String;
+    let mut channel = Channel::default();
+    let mut channel_dc = DublinCoreExtension::default();
+    let mut channel_about: Option<String> = None;
+    let mut item: Option<(Item, DublinCoreExtension)> = None;
+    let mut in_channel = false;
+    let mut reader = NsReader::from_reader(b);
+
+    buf = Vec::new();
+    text = String::new();
+    loop {
+        match reader.read_resolved_event_into(&mut buf) {
+            Ok((r, Event::Start(e))) => {
+                text.clear();
+                if ns(&r) == NS_RSS1 {
+                    match e.local_name().as_ref() {
+                        b"channel" => {
+                            in_channel = true;
+                            channel_about = about(&reader, &e);
+                        },
+                        b"item" => {
+                            item = Some((rdf_item(&reader, &e), DublinCoreExtension::default()));
+                        },
+                        _ => {},
+                    };
+                }
+            },
+            // an item without any elements, which only has the rdf:about.
+            Ok((r, Event::Empty(e))) => {
+                if ns(&r) == NS_RSS1 && e.local_name().as_ref() == b"item" {
+                    let mut v = rdf_item(&reader, &e);
+                    v.set_dublin_core_ext(Some(DublinCoreExtension::default()));
+                    channel.items.push(v);
+                }
+            },
+            Ok((_, Event::Text(e))) => {
+                match e.unescape() {
+                    Ok(v) => {
+                        text.push_str(&v);
+                    },
+                    Err(e) => {
+                        return Err(Error::new(ErrorKind::ParseError).with_source(e));
+                    },
+                };
+            },
+            Ok((_, Event::CData(e))) => {
+                text.push_str(&String::from_utf8_lossy(e.as_ref()));
+            },
+            Ok((r, Event::End(e))) => {
+                let v = String::from(text.trim());
+                let name = e.local_name();
+                let name = name.as_ref();
+                let space = ns(&r);
+
+                if space == NS_RSS1 && name == b"item" {
+                    match item.take() {
+                        Some((mut i, dc)) => {
+                            i.set_dublin_core_ext(Some(dc));
+                            channel.items.push(i);
+                        },
+                        None => {},
+                    };
+                } else {
+                    match &mut item {
+                        Some((i, dc)) => {
+                            if space == NS_RSS1 {
+                                match name {
+                                    b"title" => i.set_title(Some(v)),
+                                    b"link" => i.set_link(Some(v)),
+                                    b"description" => i.set_description(Some(v)),
+                                    _ => {},
+                                };
+                            } else if space == NS_CONTENT && name == b"encoded" {
+                                i.set_content(Some(v));
+                            } else if space == NS_DC {
+                                add_dc(dc, name, v);
+                            }
+                        },
+                        None => {
+                            if in_channel {
+                                if space == NS_RSS1 {
+                                    match name {
+                                        b"title" => channel.set_title(v),
+                                        b"link" => channel.set_link(v),
+                                        b"description" => channel.set_description(v),
+                                        b"channel" => in_channel = false,
+                                        _ => {},
+                                    };
+                                } else if space == NS_DC {
+                                    add_dc(&mut channel_dc, name, v);
+                                }
+                            }
+                        },
+                    };
+                }
+                text.clear();
+            },
+            Ok((_, Event::Eof)) => {
+                break;
+            },
+            Err(e) => {
+                return Err(Error::new(ErrorKind::ParseError).with_source(e));
+            },
+            _ => {},
+        };
+        buf.clear();
+    }
+    debug!("have RSS 1.0 feed with {} items", channel.items.len());
+
+    channel.set_dublin_core_ext(Some(channel_dc));
+    o = translate(channel, uri, allow_entry_fail, date_fallback)?;
+
+    match channel_about {
+        Some(v) => {
+            o.set_id(v);
+        },
+        None => {},
+    };
+    Ok(o)
+}
@@ -1,8 +1,17 @@
-use std::path::Path;
-use std::fs::File;
 use std::io::BufReader;
-use std::io::BufRead;
 use crate::Error;
+use crate::ErrorKind;
+use crate::json::is_json;
+use crate::json::from_bytes as json_from_bytes;
+use crate::rdf::is_rdf;
+use crate::rdf::from_bytes as rdf_from_bytes;
+use crate::date::parse as parse_date;
+use crate::date::normalize_atom;
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use quick_xml::events::BytesStart;
+use quick_xml::escape::unescape_with;
 
 use log::info;
 use log::debug;
@@ -10,54 +19,95 @@ use log::error;
 
 use rss::Channel;
 use rss::Item;
+use rss::Enclosure;
+use rss::Guid;
+use rss::Source;
+use rss::Category as RssCategory;
 use rss::extension::dublincore::DublinCoreExtension;
 use atom_syndication::Feed;
+use atom_syndication::Error as AtomError;
 use atom_syndication::Entry;
 use atom_syndication::Text;
 use atom_syndication::TextType;
 use atom_syndication::FixedDateTime;
 use atom_syndication::Content;
 use atom_syndication::Category;
-use chrono::naive::NaiveDateTime;
+use atom_syndication::Link;
+use atom_syndication::Person;
+use atom_syndication::Generator;
 use chrono::Local;
-use chrono::offset::Utc;
+use chrono::TimeDelta;
+use serde::Deserialize;
+use uuid::Uuid;
 
-/// try to coerce the item field into a valid date
-fn parse_date(v: &String) -> Result<FixedDateTime, Error> {
-    match FixedDateTime::parse_from_rfc2822(v.as_str()) {
-        Ok(r) => {
-            return Ok(r);
-        },
-        Err(e) => {},
-    };
-    match FixedDateTime::parse_from_rfc3339(v.as_str()) {
-        Ok(r) => {
-            return Ok(r);
-        },
-        Err(e) => {},
-    };
-    match FixedDateTime::parse_from_str(v.as_str(), "%Y-%m-%dT%H:%M:%S") {
-        Ok(r) => {
-            return Ok(r);
-        },
-        Err(e) => {
-        },
-    };
-    match NaiveDateTime::parse_from_str(v.as_str(), "%Y-%m-%dT%H:%M:%S") {
-        Ok(r) => {
-            return Ok(r.and_utc().fixed_offset());
-        },
-        Err(e) => {
-        },
-    };
+use crate::namespace;
 
+/// Category scheme marking entries whose date was not given by the source.
+///
+/// The term of the category names the `DateFallback` that provided the date.
+pub static DATE_FALLBACK_SCHEME: &str = "https://defalsify.org/src/crier/date-fallback";
 
-    Err(Error::ParseError)
+/// What to do with items that have no usable date.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFallback {
+    /// Fail the item.
+    #[default]
+    Reject,
+    /// Use the lastBuildDate or pubDate of the channel, and fail the item if it has neither.
+    Channel,
+    /// Use the time the feed was read. The date changes on every read.
+    Fetched,
+    /// Use the channel date, or the time the feed was read, less one second per preceding item,
+    /// so that the entries keep the order of the document.
+    Position,
+}
+
+impl DateFallback {
+    fn term(&self) -> &'static str {
+        match self {
+            DateFallback::Reject => "reject",
+            DateFallback::Channel => "channel",
+            DateFallback::Fetched => "fetched",
+            DateFallback::Position => "position",
+        }
+    }
+
+    /// The date for an undated item at the given position in the document, if the fallback
+    /// provides one.
+    pub(crate) fn date(&self, channel: Option<FixedDateTime>, fetched: FixedDateTime, position: usize) -> Option<FixedDateTime> {
+        match self {
+            DateFallback::Reject => {
+                None
+            },
+            DateFallback::Channel => {
+                channel
+            },
+            DateFallback::Fetched => {
+                Some(fetched)
+            },
+            DateFallback::Position => {
+                Some(channel.unwrap_or(fetched) - TimeDelta::seconds(position as i64))
+            },
+        }
+    }
+
+    /// Give the entry the fallback date, and mark it with a category in `DATE_FALLBACK_SCHEME`.
+    pub(crate) fn apply(&self, entry: &mut Entry, date: FixedDateTime) {
+        let mut cat = Category::default();
+
+        entry.set_published(date.clone());
+        entry.set_updated(date);
+        cat.set_term(self.term());
+        cat.set_scheme(Some(String::from(DATE_FALLBACK_SCHEME)));
+        entry.categories.push(cat);
+    }
 }
 
 /// try different item fields to determine the date
 fn get_base_date(ipt: &Item) -> Result<FixedDateTime, Error> {
     let mut ds = String::new();
+    let mut err: Option<Error> = None;
 
     match &ipt.pub_date {
         Some(v) => {
@@ -69,7 +119,11 @@ fn get_base_date(ipt: &Item) -> Result<FixedDateTime, Error> {
         Ok(v) => {
             return Ok(v);
         },
-        Err(e) => {},
+        Err(e) => {
+            if !ds.is_empty() {
+                err = Some(e);
+            }
+        },
     };
 
     match &ipt.dublin_core_ext {
@@ -81,6 +135,7 @@ fn get_base_date(ipt: &Item) -> Result<FixedDateTime, Error> {
                     },
                     Err(e) => {
                         debug!("no date");
+                        err = Some(e);
                     },
                 }
             }
@@ -88,39 +143,343 @@ fn get_base_date(ipt: &Item) -> Result<FixedDateTime, Error> {
         _ => {},
     }
 
-    Err(Error::IncompleteError)
+    match err {
+        Some(e) => {
+            Err(e)
+        },
+        None => {
+            Err(Error::new(ErrorKind::IncompleteError).with_source("item has no date"))
+        },
+    }
+}
+
+/// best available identification of the item for diagnostics.
+fn item_ident(ipt: &Item) -> String {
+    match &ipt.guid {
+        Some(v) => {
+            return String::from(v.value());
+        },
+        None => {},
+    };
+    match &ipt.link {
+        Some(v) => {
+            return v.clone();
+        },
+        None => {},
+    };
+    match &ipt.title {
+        Some(v) => {
+            v.clone()
+        },
+        None => {
+            String::new()
+        },
+    }
+}
+
+/// split an rss author, "email (name)" by convention, into an atom person.
+///
+/// The reverse "name (email)" is also seen in the wild, so the address is taken from whichever
+/// part looks like one.
+fn author_person(v: &str) -> Person {
+    let s = v.trim();
+
+    match s.find('(') {
+        Some(i) => {
+            if s.ends_with(')') {
+                let mut name = s[i+1..s.len()-1].trim();
+                let mut email = s[..i].trim();
+                if name.contains('@') && !email.contains('@') {
+                    (name, email) = (email, name);
+                }
+                if email.is_empty() {
+                    return Person{
+                        name: String::from(name),
+                        email: None,
+                        uri: None,
+                    };
+                }
+                return Person{
+                    name: String::from(name),
+                    email: Some(String::from(email)),
+                    uri: None,
+                };
+            }
+        },
+        None => {},
+    };
+    if s.contains('@') {
+        return Person{
+            name: String::from(s),
+            email: Some(String::from(s)),
+            uri: None,
+        };
+    }
+    Person{
+        name: String::from(s),
+        email: None,
+        uri: None,
+    }
+}
+
+fn translate_category(ipt: RssCategory) -> Category {
+    let mut cat = Category::default();
+
+    cat.set_term(String::from(ipt.name()));
+    cat.set_label(Some(ipt.name));
+    match ipt.domain {
+        Some(v) => {
+            cat.set_scheme(Some(v));
+        },
+        _ => {},
+    };
+    cat
+}
+
+/// true if the string has tags or character references, which would show verbatim as text.
+fn has_markup(s: &str) -> bool {
+    let b = s.as_bytes();
+
+    for (i, c) in b.iter().enumerate() {
+        let rest = &b[i+1..];
+        match c {
+            b'<' => {
+                match rest.first() {
+                    Some(v) => {
+                        if v.is_ascii_alphabetic() || *v == b'/' || *v == b'!' {
+                            return true;
+                        }
+                    },
+                    None => {},
+                };
+            },
+            b'&' => {
+                let name = match rest.strip_prefix(b"#") {
+                    Some(v) => v,
+                    None => rest,
+                };
+                let n = name.iter().take_while(|v| v.is_ascii_alphanumeric()).count();
+                if n > 0 && name.get(n) == Some(&b';') {
+                    return true;
+                }
+            },
+            _ => {},
+        };
+    }
+    false
+}
+
+fn is_xml_attributes(tag: &BytesStart, reader: &Reader<&[u8]>) -> bool {
+    for v in tag.attributes() {
+        match v {
+            Ok(vv) => {
+                if vv.decode_and_unescape_value(reader).is_err() {
+                    return false;
+                }
+            },
+            Err(_) => {
+                return false;
+            },
+        };
+    }
+    true
+}
+
+/// true if the string is a well-formed xml fragment using only the predefined entities.
+fn is_xml_fragment(s: &str) -> bool {
+    let mut depth: usize;
+    let doc = format!("<div>{}</div>", s);
+    let mut reader = Reader::from_str(doc.as_str());
+
+    depth = 0;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(v)) => {
+                depth += 1;
+                if !is_xml_attributes(&v, &reader) {
+                    return false;
+                }
+            },
+            Ok(Event::Empty(v)) => {
+                if !is_xml_attributes(&v, &reader) {
+                    return false;
+                }
+            },
+            Ok(Event::End(_)) => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            },
+            Ok(Event::Text(v)) => {
+                if v.unescape().is_err() {
+                    return false;
+                }
+            },
+            Ok(Event::Decl(_)) | Ok(Event::PI(_)) | Ok(Event::DocType(_)) => {
+                return false;
+            },
+            Ok(Event::Eof) => {
+                return depth == 0;
+            },
+            Ok(_) => {},
+            Err(_) => {
+                return false;
+            },
+        };
+    }
+}
+
+/// atom text for the title or description of an rss channel or item.
+///
+/// Text without markup stays plain. Markup that is well-formed xml becomes xhtml when `xhtml`
+/// is set, and any other markup, including escaped html, becomes html.
+fn translate_text(v: &str, xhtml: bool) -> Text {
+    if !has_markup(v) {
+        return Text::plain(v);
+    }
+    if xhtml && is_xml_fragment(v) {
+        return Text::xhtml(format!("<div xmlns=\"http://www.w3.org/1999/xhtml\">{}</div>", v));
+    }
+    Text::html(v)
+}
+
+/// the markup inside the div that wraps atom xhtml.
+pub(crate) fn unwrap_xhtml(s: &str) -> &str {
+    let v = s.trim();
+
+    if !v.ends_with("</div>") {
+        return s;
+    }
+    match v.strip_prefix("<div") {
+        Some(rest) if rest.starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) => {
+            match rest.find('>') {
+                Some(i) => {
+                    rest[i+1..rest.len()-6].trim()
+                },
+                None => {
+                    s
+                },
+            }
+        },
+        _ => {
+            s
+        },
+    }
+}
+
+/// an atom text as html; xhtml without its wrapping div.
+pub(crate) fn text_html(v: &Text) -> String {
+    match v.r#type {
+        TextType::Xhtml => {
+            String::from(unwrap_xhtml(v.value.as_str()))
+        },
+        _ => {
+            v.value.clone()
+        },
+    }
+}
+
+/// an atom text as plain text, with tags dropped and character references resolved.
+pub(crate) fn text_plain(v: &Text) -> String {
+    let mut r: String;
+    let mut tag: bool;
+
+    if v.r#type == TextType::Text {
+        return v.value.clone();
+    }
+    r = String::new();
+    tag = false;
+    for c in v.value.chars() {
+        match c {
+            '<' => {
+                tag = true;
+            },
+            '>' if tag => {
+                tag = false;
+            },
+            _ if !tag => {
+                r.push(c);
+            },
+            _ => {},
+        };
+    }
+    let resolve = |name: &str| match name {
+        "nbsp" => Some("\u{a0}"),
+        "mdash" => Some("\u{2014}"),
+        "ndash" => Some("\u{2013}"),
+        "hellip" => Some("\u{2026}"),
+        _ => None,
+    };
+    match unescape_with(r.as_str(), resolve) {
+        Ok(v) => {
+            String::from(v.trim())
+        },
+        Err(e) => {
+            debug!("leaving unknown references in text: {}", e);
+            String::from(r.trim())
+        },
+    }
+}
+
+/// id for an item with neither guid nor link, derived from the source uri, title, text and date.
+///
+/// Dates supplied by a `DateFallback` are not stable across reads and are left out, so the text
+/// keeps undated items of the same title apart.
+fn synthesize_id(uri: &str, title: &str, text: &str, date: Option<&FixedDateTime>) -> String {
+    let v = format!("{}\n{}\n{}\n{}", uri, title, text, date.map(|v| v.to_rfc3339()).unwrap_or_default());
+    Uuid::new_v5(&namespace(), v.as_bytes()).urn().to_string()
 }
 
 /// coerce the rss item into an atom entry
-fn translate_item(ipt: Item) -> Result<Entry, Error> {
+///
+/// The guid is used as id whether or not it is a permalink, then the link. If the item has
+/// neither, the id is derived from the uri of the source, the title, the description or content
+/// and the date.
+///
+/// An item without a usable date gets the fallback date if one is given, and is marked with
+/// a category in `DATE_FALLBACK_SCHEME`.
+fn translate_item(ipt: Item, uri: &str, fallback: Option<(FixedDateTime, DateFallback)>) -> Result<Entry, Error> {
+    let date: Option<FixedDateTime>;
+
     let mut opt = Entry::default();
 
     match &ipt.title {
         Some(v) => {
-            opt.set_title(Text::plain(v));
+            opt.set_title(translate_text(v, false));
         },
         _ => {},
     };
 
     match get_base_date(&ipt) {
         Ok(v) => {
+            date = Some(v);
             opt.set_published(v.clone());
             opt.set_updated(v);
         },
         Err(e) => {
-            return Err(e);
+            match fallback {
+                Some((v, kind)) => {
+                    debug!("item {} has no usable date, using {} date: {}", item_ident(&ipt), kind.term(), e);
+                    date = None;
+                    kind.apply(&mut opt, v);
+                },
+                None => {
+                    return Err(e);
+                },
+            };
         }
     };
    
     match ipt.description {
         Some(v) => {
-            opt.set_summary(Some(Text::xhtml(v)));
+            opt.set_summary(Some(translate_text(v.as_str(), true)));
         },
         _ => {
             match ipt.content {
                 Some(v) => {
                     let mut r = Content::default();
-                    r.set_content_type(Some(String::from("text/html")));
+                    r.set_content_type(Some(String::from("html")));
                     r.set_value(Some(v));
                     match ipt.source {
                         Some(v) => {
@@ -132,124 +491,466 @@ fn translate_item(ipt: Item) -> Result<Entry, Error> {
                 },
                 _ => {
                     error!("have neither summary nor content");
-                    return Err(Error::IncompleteError);
+                    return Err(Error::new(ErrorKind::IncompleteError).with_source("item has neither description nor content"));
                 },
             };
         },
     };
 
-    match ipt.guid {
-        Some(v) => {
-            if v.is_permalink() {
-                opt.set_id(String::from(v.value()));
-            }
+    match &ipt.guid {
+        Some(v) if !v.value().trim().is_empty() => {
+            opt.set_id(String::from(v.value().trim()));
         },
         _ => {
-            match ipt.link {
-                Some(v) => {
-                    opt.set_id(v.clone());
+            match &ipt.link {
+                Some(v) if !v.trim().is_empty() => {
+                    opt.set_id(String::from(v.trim()));
+                },
+                _ => {
+                    let text = match (&opt.summary, &opt.content) {
+                        (Some(v), _) => {
+                            v.value.as_str()
+                        },
+                        (None, Some(v)) => {
+                            v.value.as_deref().unwrap_or_default()
+                        },
+                        (None, None) => {
+                            ""
+                        },
+                    };
+                    opt.set_id(synthesize_id(uri, opt.title.value.as_str(), text, date.as_ref()));
                 },
-                _ => {},
             }
         },
     };
 
+    match &ipt.link {
+        Some(v) => {
+            let mut link = Link::default();
+            link.set_href(v.as_str());
+            link.set_rel("alternate");
+            opt.links.push(link);
+        },
+        None => {},
+    };
+
+    match &ipt.enclosure {
+        Some(v) => {
+            let mut link = Link::default();
+            link.set_href(v.url());
+            link.set_rel("enclosure");
+            if !v.mime_type().is_empty() {
+                link.set_mime_type(Some(String::from(v.mime_type())));
+            }
+            if !v.length().is_empty() && v.length() != "0" {
+                link.set_length(Some(String::from(v.length())));
+            }
+            opt.links.push(link);
+        },
+        None => {},
+    };
+
+    match &ipt.comments {
+        Some(v) => {
+            let mut link = Link::default();
+            link.set_href(v.as_str());
+            link.set_rel("replies");
+            link.set_mime_type(Some(String::from("text/html")));
+            opt.links.push(link);
+        },
+        None => {},
+    };
+
+    match &ipt.author {
+        Some(v) => {
+            opt.authors.push(author_person(v));
+        },
+        None => {},
+    };
+
+    match &ipt.dublin_core_ext {
+        Some(v) => {
+            for vv in v.creators() {
+                if opt.authors.iter().any(|p| p.name == *vv) {
+                    continue;
+                }
+                opt.authors.push(Person{
+                    name: vv.clone(),
+                    email: None,
+                    uri: None,
+                });
+            }
+            for vv in v.contributors() {
+                opt.contributors.push(Person{
+                    name: vv.clone(),
+                    email: None,
+                    uri: None,
+                });
+            }
+            for vv in v.subjects() {
+                let mut cat = Category::default();
+                cat.set_term(vv.clone());
+                opt.categories.push(cat);
+            }
+            match v.rights().first() {
+                Some(vv) => {
+                    opt.set_rights(Some(Text::plain(vv)));
+                },
+                None => {},
+            };
+        },
+        None => {},
+    };
+
     for v in ipt.categories {
-        let mut cat = Category::default();
-        cat.set_term(String::from(v.name()));
-        cat.set_label(Some(v.name));
-        match v.domain {
-            Some(v) => {
-                cat.set_scheme(Some(v));
-            },
-            _ => {},
-        };
-        opt.categories.push(cat);
+        opt.categories.push(translate_category(v));
     }
 
     Ok(opt)
 }
 
 
-fn translate(ipt: Channel, allow_fail: bool) -> Result<Feed, Error> {
+pub(crate) fn translate(ipt: Channel, uri: &str, allow_fail: bool, date_fallback: DateFallback) -> Result<Feed, Error> {
     let mut entries: Vec<Entry>;
     let mut opt = Feed::default();
+    let mut updated: Option<FixedDateTime> = None;
     
-    opt.set_title(Text::plain(&ipt.title));
+    opt.set_title(translate_text(&ipt.title, false));
+
+    opt.set_subtitle(Some(translate_text(&ipt.description, false)));
 
-    opt.set_subtitle(Some(Text::plain(&ipt.description)));
+    if !ipt.link.is_empty() {
+        let mut link = Link::default();
+        link.set_href(ipt.link.as_str());
+        link.set_rel("alternate");
+        opt.links.push(link);
+        opt.set_id(ipt.link.clone());
+    }
+
+    opt.set_lang(ipt.language.clone());
 
+    match &ipt.managing_editor {
+        Some(v) => {
+            opt.authors.push(author_person(v));
+        },
+        None => {},
+    };
+
+    // rss has a single image, used both as the larger logo and the small icon.
+    match &ipt.image {
+        Some(v) => {
+            opt.set_logo(Some(v.url.clone()));
+            opt.set_icon(Some(v.url.clone()));
+        },
+        None => {},
+    };
+
+    match &ipt.generator {
+        Some(v) => {
+            let mut generator = Generator::default();
+            generator.set_value(v.as_str());
+            opt.set_generator(Some(generator));
+        },
+        None => {},
+    };
+
+    match &ipt.copyright {
+        Some(v) => {
+            opt.set_rights(Some(Text::plain(v)));
+        },
+        None => {},
+    };
+
+    match &ipt.dublin_core_ext {
+        Some(v) => {
+            for vv in v.creators() {
+                if opt.authors.iter().any(|p| p.name == *vv) {
+                    continue;
+                }
+                opt.authors.push(Person{
+                    name: vv.clone(),
+                    email: None,
+                    uri: None,
+                });
+            }
+            if opt.rights.is_none() {
+                opt.set_rights(v.rights().first().map(|vv| Text::plain(vv)));
+            }
+            for vv in v.dates() {
+                match parse_date(vv) {
+                    Ok(r) => {
+                        updated = Some(r);
+                        break;
+                    },
+                    Err(_e) => {},
+                };
+            }
+        },
+        None => {},
+    };
+
+    for v in ipt.categories.iter() {
+        opt.categories.push(translate_category(v.clone()));
+    }
+
+    // lastBuildDate is when the channel last changed, pubDate when its content was published.
+    for v in ipt.last_build_date.iter().chain(ipt.pub_date.iter()) {
+        match parse_date(v) {
+            Ok(r) => {
+                updated = Some(r);
+                break;
+            },
+            Err(e) => {
+                debug!("invalid channel date: {}", e);
+            },
+        };
+    }
+
+    let fetched = Local::now().fixed_offset();
     entries = vec!();
-    for v in ipt.into_items() {
-        match translate_item(v) {
+    for (i, v) in ipt.into_items().into_iter().enumerate() {
+        let ident = item_ident(&v);
+        let fallback = date_fallback.date(updated, fetched, i);
+        match translate_item(v, uri, fallback.map(|v| (v, date_fallback))) {
             Ok(v) => {
                 entries.push(v);
             },
             Err(e) => {
                 if !allow_fail {
-                    return Err(Error::IncompleteError);
+                    return Err(e.with_entry(ident.as_str()));
                 }
+                info!("skipping item {}: {}", ident, e);
             },
         }
     }
 
     opt.set_entries(entries);
-    opt.set_updated(Local::now().to_utc());
+    match updated {
+        Some(v) => {
+            opt.set_updated(v);
+        },
+        None => {
+            opt.set_updated(Local::now().to_utc());
+        },
+    };
     Ok(opt)
 }
 
-pub fn from_file(fp: &str, allow_entry_fail: bool) -> Result<Feed, Error> {
-    let mut o: Channel;
-    let r: Feed;
-    let p: &Path; 
-    let mut f: File;
-    //let mut b: BufReader; // how to explicitly declare 
+/// parse a document already in memory, retrieved from the given uri.
+///
+/// JSON Feed is detected by the document being json and RSS 1.0 by its rdf root element,
+/// otherwise atom is tried first and rss second.
+pub fn from_bytes(b: &[u8], uri: &str, allow_entry_fail: bool, date_fallback: DateFallback) -> Result<Feed, Error> {
+    let o: Channel;
 
-    p = Path::new(fp);
-    f = File::open(p).unwrap();
-    let mut b = BufReader::new(f);
+    if is_json(b) {
+        return json_from_bytes(b, allow_entry_fail, date_fallback);
+    }
+    if is_rdf(b) {
+        return rdf_from_bytes(b, uri, allow_entry_fail, date_fallback);
+    }
 
-    match Feed::read_from(b) {
+    let atom_err = match Feed::read_from(BufReader::new(b)) {
         Ok(v) => {
             debug!("have atom feed");
             return Ok(v);
         },
-        Err(e) => {},
+        Err(AtomError::WrongDatetime(v)) => {
+            debug!("atom feed has invalid date '{}', normalizing", v);
+            match normalize_atom(b) {
+                Ok(r) => {
+                    match Feed::read_from(BufReader::new(r.as_slice())) {
+                        Ok(v) => {
+                            debug!("have atom feed");
+                            return Ok(v);
+                        },
+                        Err(e) => e,
+                    }
+                },
+                Err(e) => {
+                    return Err(e);
+                },
+            }
+        },
+        Err(e) => e,
     };
 
-    f = File::open(p).unwrap();
-    b = BufReader::new(f);
-
-    match Channel::read_from(b) {
+    match Channel::read_from(BufReader::new(b)) {
         Ok(v) => {
             debug!("have RSS feed");
             o = v;
         },
         Err(e) => {
-            return Err(Error::ParseError);
+            return Err(Error::new(ErrorKind::ParseError).with_source(format!("neither atom ({}) nor rss ({})", atom_err, e)));
         },
     };
-    o.set_dublin_core_ext(DublinCoreExtension::default());
-    translate(o, allow_entry_fail)
+    translate(o, uri, allow_entry_fail, date_fallback)
+}
+
+/// the preferred link among the given atom links; alternate if available, otherwise the first
+/// one that is not an enclosure.
+fn alternate_link(links: &[Link]) -> Option<String> {
+    match links.iter().find(|v| v.rel == "alternate") {
+        Some(v) => {
+            return Some(v.href.clone());
+        },
+        None => {},
+    };
+    links.iter().find(|v| v.rel != "enclosure").map(|v| v.href.clone())
+}
+
+/// coerce the atom entry into an rss item
+fn entry_to_item(ipt: &Entry) -> Item {
+    let mut opt = Item::default();
+    let mut creators: Vec<String>;
+
+    opt.set_title(Some(text_plain(&ipt.title)));
+    opt.set_link(alternate_link(&ipt.links));
+
+    match ipt.links.iter().find(|v| v.rel == "enclosure") {
+        Some(v) => {
+            let mut r = Enclosure::default();
+            r.set_url(v.href.clone());
+            r.set_length(v.length.clone().unwrap_or(String::from("0")));
+            r.set_mime_type(v.mime_type.clone().unwrap_or(String::from("application/octet-stream")));
+            opt.set_enclosure(Some(r));
+        },
+        None => {},
+    };
+
+    match &ipt.summary {
+        Some(v) => {
+            opt.set_description(Some(text_html(v)));
+        },
+        None => {},
+    };
+    match &ipt.content {
+        Some(v) => {
+            match v.content_type.as_deref() {
+                Some("xhtml") => {
+                    opt.set_content(v.value.as_deref().map(|vv| String::from(unwrap_xhtml(vv))));
+                },
+                _ => {
+                    opt.set_content(v.value.clone());
+                },
+            };
+        },
+        None => {},
+    };
+
+    for v in ipt.categories.iter() {
+        let mut cat = RssCategory::default();
+        cat.set_name(v.label.clone().unwrap_or(v.term.clone()));
+        cat.set_domain(v.scheme.clone());
+        opt.categories.push(cat);
+    }
+
+    // rss authors are email addresses, so authors without one are only named in dc:creator.
+    creators = Vec::new();
+    for v in ipt.authors.iter() {
+        match &v.email {
+            Some(email) => {
+                if opt.author.is_none() {
+                    opt.set_author(Some(format!("{} ({})", email, v.name)));
+                }
+            },
+            None => {},
+        };
+        creators.push(v.name.clone());
+    }
+    if creators.len() > 0 {
+        let mut dc = DublinCoreExtension::default();
+        dc.set_creators(creators);
+        opt.set_dublin_core_ext(Some(dc));
+    }
+
+    match &ipt.published {
+        Some(v) => {
+            opt.set_pub_date(Some(v.to_rfc2822()));
+        },
+        None => {
+            opt.set_pub_date(Some(ipt.updated.to_rfc2822()));
+        },
+    };
+
+    let mut guid = Guid::default();
+    guid.set_value(ipt.id.clone());
+    guid.set_permalink(ipt.id.starts_with("http://") || ipt.id.starts_with("https://"));
+    opt.set_guid(Some(guid));
+
+    match &ipt.source {
+        Some(v) => {
+            let url = match v.links.iter().find(|l| l.rel == "self") {
+                Some(l) => Some(l.href.clone()),
+                None => alternate_link(&v.links),
+            };
+            match url {
+                Some(u) => {
+                    let mut r = Source::default();
+                    r.set_url(u);
+                    r.set_title(Some(v.title.value.clone()));
+                    opt.set_source(Some(r));
+                },
+                None => {},
+            };
+        },
+        None => {},
+    };
+
+    opt
+}
+
+/// coerce the atom feed into an rss channel
+pub(crate) fn to_channel(ipt: &Feed) -> Channel {
+    let mut opt = Channel::default();
+
+    opt.set_title(text_plain(&ipt.title));
+    opt.set_link(alternate_link(&ipt.links).unwrap_or(ipt.id.clone()));
+    match &ipt.subtitle {
+        Some(v) => {
+            opt.set_description(text_html(v));
+        },
+        None => {
+            opt.set_description(text_plain(&ipt.title));
+        },
+    };
+    opt.set_last_build_date(Some(ipt.updated.to_rfc2822()));
+    match &ipt.generator {
+        Some(v) => {
+            match &v.version {
+                Some(version) => {
+                    opt.set_generator(Some(format!("{} {}", v.value, version)));
+                },
+                None => {
+                    opt.set_generator(Some(v.value.clone()));
+                },
+            };
+        },
+        None => {},
+    };
+
+    opt.set_items(ipt.entries.iter().map(|v| entry_to_item(v)).collect::<Vec<Item>>());
+    opt
 }
 
 mod test {
     use std::path::Path;
     use atom_syndication::Feed;
+    use atom_syndication::TextType;
     use env_logger;
 
     #[test]
     fn test_rss_from_file() {
         env_logger::init();
         let mut r: Feed;
-        match super::from_file("testdata/test.rss.xml", false) {
+        match super::from_bytes(&std::fs::read("testdata/test.rss.xml").unwrap(), "testdata/test.rss.xml", false, super::DateFallback::Reject) {
             Ok(v) => {
             },
             Err(e) => {
                 panic!("{:?}", e);
             },
         };
-        match super::from_file("testdata/test.atom.xml", false) {
+        match super::from_bytes(&std::fs::read("testdata/test.atom.xml").unwrap(), "testdata/test.atom.xml", false, super::DateFallback::Reject) {
             Ok(v) => {
             },
             Err(e) => {
@@ -257,4 +958,208 @@ mod test {
             },
         };
     }
+
+    #[test]
+    fn test_rss_entry_error() {
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>baz</title><guid>https://example.com/baz</guid><description>xyzzy</description></item>\
+            </channel></rss>";
+        let e = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap_err();
+        assert_eq!(e.kind(), crate::ErrorKind::IncompleteError);
+        assert_eq!(e.entry().unwrap(), "https://example.com/baz");
+        assert_eq!(e.to_string(), "missing required data in entry https://example.com/baz: item has no date");
+
+        let feed = super::from_bytes(src, "", true, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.entries.len(), 0);
+    }
+
+    #[test]
+    fn test_rss_content_encoded() {
+        let src = b"<rss version=\"2.0\" xmlns:content=\"http://purl.org/rss/1.0/modules/content/\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>baz</title><guid>https://example.com/baz</guid><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate>\
+            <content:encoded><![CDATA[<p>xyzzy</p>]]></content:encoded></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap();
+        let content = feed.entries[0].content.as_ref().unwrap();
+        assert_eq!(content.content_type.as_ref().unwrap(), "html");
+        assert_eq!(content.value.as_ref().unwrap(), "<p>xyzzy</p>");
+    }
+
+    #[test]
+    fn test_rss_item_links() {
+        let src = b"<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>baz</title><link>https://example.com/baz.html</link><guid>https://example.com/baz</guid><description>xyzzy</description>\
+            <pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate><author>inky@example.com (Inky)</author><dc:creator>Clyde</dc:creator>\
+            <enclosure url=\"https://example.com/baz.ogg\" length=\"42\" type=\"audio/ogg\"/><comments>https://example.com/baz#comments</comments></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap();
+        let entry = &feed.entries[0];
+        assert_eq!(entry.links.len(), 3);
+        assert_eq!(entry.links[0].href, "https://example.com/baz.html");
+        assert_eq!(entry.links[0].rel, "alternate");
+        assert_eq!(entry.links[1].rel, "enclosure");
+        assert_eq!(entry.links[1].mime_type.as_ref().unwrap(), "audio/ogg");
+        assert_eq!(entry.links[1].length.as_ref().unwrap(), "42");
+        assert_eq!(entry.links[2].rel, "replies");
+        assert_eq!(entry.authors.len(), 2);
+        assert_eq!(entry.authors[0].name, "Inky");
+        assert_eq!(entry.authors[0].email.as_ref().unwrap(), "inky@example.com");
+        assert_eq!(entry.authors[1].name, "Clyde");
+    }
+
+    #[test]
+    fn test_rss_author() {
+        let p = super::author_person("inky@example.com (Inky)");
+        assert_eq!(p.name, "Inky");
+        assert_eq!(p.email.as_ref().unwrap(), "inky@example.com");
+
+        let p = super::author_person("Pinky (pinky@example.com)");
+        assert_eq!(p.name, "Pinky");
+        assert_eq!(p.email.as_ref().unwrap(), "pinky@example.com");
+
+        let p = super::author_person("(Blinky)");
+        assert_eq!(p.name, "Blinky");
+        assert!(p.email.is_none());
+
+        let p = super::author_person("Clyde");
+        assert_eq!(p.name, "Clyde");
+        assert!(p.email.is_none());
+
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>baz</title><guid>https://example.com/baz</guid><description>xyzzy</description>\
+            <pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate><author>inky@example.com (Inky)</author></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap();
+        let channel = super::to_channel(&feed);
+        assert_eq!(channel.items[0].author.as_ref().unwrap(), "inky@example.com (Inky)");
+
+        let mut b: Vec<u8> = Vec::new();
+        channel.write_to(&mut b).unwrap();
+        let feed = super::from_bytes(b.as_slice(), "", false, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.entries[0].authors[0].name, "Inky");
+        assert_eq!(feed.entries[0].authors[0].email.as_ref().unwrap(), "inky@example.com");
+    }
+
+    #[test]
+    fn test_rss_channel_meta() {
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <language>en-gb</language><copyright>CC-BY</copyright><managingEditor>inky@example.com (Inky)</managingEditor>\
+            <image><url>https://example.com/logo.png</url><title>foo</title><link>https://example.com/</link></image>\
+            <category domain=\"https://example.com/tags\">ghosts</category><generator>pacman</generator>\
+            <lastBuildDate>Wed, 26 Jun 2024 20:46:00 +0200</lastBuildDate></channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.id, "https://example.com/");
+        assert_eq!(feed.links[0].rel, "alternate");
+        assert_eq!(feed.lang.as_ref().unwrap(), "en-gb");
+        assert_eq!(feed.rights.as_ref().unwrap().value, "CC-BY");
+        assert_eq!(feed.authors[0].name, "Inky");
+        assert_eq!(feed.logo.as_ref().unwrap(), "https://example.com/logo.png");
+        assert_eq!(feed.icon.as_ref().unwrap(), "https://example.com/logo.png");
+        assert_eq!(feed.categories[0].scheme.as_ref().unwrap(), "https://example.com/tags");
+        assert_eq!(feed.generator.as_ref().unwrap().value, "pacman");
+        assert_eq!(feed.updated.to_rfc3339(), "2024-06-26T20:46:00+02:00");
+    }
+
+    #[test]
+    fn test_rss_channel_dc() {
+        let src = b"<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <dc:creator>Inky</dc:creator><dc:rights>CC-BY</dc:rights><dc:date>2024-06-26T20:46:00+02:00</dc:date>\
+            <item><title>baz</title><guid>https://example.com/baz</guid><description>xyzzy</description></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Channel).unwrap();
+        assert_eq!(feed.authors[0].name, "Inky");
+        assert_eq!(feed.rights.as_ref().unwrap().value, "CC-BY");
+        assert_eq!(feed.updated.to_rfc3339(), "2024-06-26T20:46:00+02:00");
+        assert_eq!(feed.entries[0].updated.to_rfc3339(), "2024-06-26T20:46:00+02:00");
+    }
+
+    #[test]
+    fn test_rss_item_id() {
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>inky</title><guid isPermaLink=\"false\">inky-1</guid><link>https://example.com/inky</link><description>xyzzy</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>pinky</title><link>https://example.com/pinky</link><description>xyzzy</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>blinky</title><description>xyzzy</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>clyde</title><description>xyzzy</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "https://example.com/feed.xml", false, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.entries[0].id, "inky-1");
+        assert_eq!(feed.entries[1].id, "https://example.com/pinky");
+        assert!(feed.entries[2].id.starts_with("urn:uuid:"));
+        assert_ne!(feed.entries[2].id, feed.entries[3].id);
+
+        let feed_again = super::from_bytes(src, "https://example.com/feed.xml", false, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.entries[2].id, feed_again.entries[2].id);
+        let feed_other = super::from_bytes(src, "https://example.org/feed.xml", false, super::DateFallback::Reject).unwrap();
+        assert_ne!(feed.entries[2].id, feed_other.entries[2].id);
+    }
+
+    #[test]
+    fn test_rss_date_fallback() {
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <lastBuildDate>Wed, 26 Jun 2024 20:46:00 +0200</lastBuildDate>\
+            <item><title>inky</title><guid>inky</guid><description>xyzzy</description></item>\
+            <item><title>pinky</title><guid>pinky</guid><description>xyzzy</description><pubDate>Tue, 25 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>blinky</title><description>xyzzy</description></item>\
+            </channel></rss>";
+        let e = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap_err();
+        assert_eq!(e.kind(), crate::ErrorKind::IncompleteError);
+
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Channel).unwrap();
+        assert_eq!(feed.entries[0].updated.to_rfc3339(), "2024-06-26T20:46:00+02:00");
+        assert_eq!(feed.entries[0].categories[0].term, "channel");
+        assert_eq!(feed.entries[0].categories[0].scheme.as_ref().unwrap(), super::DATE_FALLBACK_SCHEME);
+        assert_eq!(feed.entries[1].updated.to_rfc3339(), "2024-06-25T20:46:00+02:00");
+        assert!(feed.entries[1].categories.is_empty());
+
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Position).unwrap();
+        assert_eq!(feed.entries[0].updated.to_rfc3339(), "2024-06-26T20:46:00+02:00");
+        assert_eq!(feed.entries[2].updated.to_rfc3339(), "2024-06-26T20:45:58+02:00");
+        assert_eq!(feed.entries[2].categories[0].term, "position");
+
+        // the id of an undated item does not depend on the fallback date.
+        let feed_fetched = super::from_bytes(src, "", false, super::DateFallback::Fetched).unwrap();
+        assert_eq!(feed_fetched.entries[2].categories[0].term, "fetched");
+        assert_eq!(feed_fetched.entries[2].id, feed.entries[2].id);
+
+        // nor do undated items of the same title share an id.
+        let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>inky</title><description>xyzzy</description></item>\
+            <item><title>inky</title><description>plugh</description></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Fetched).unwrap();
+        assert_ne!(feed.entries[0].id, feed.entries[1].id);
+    }
+
+    #[test]
+    fn test_rss_text_type() {
+        let src = b"<rss version=\"2.0\"><channel><title>foo &amp; bar</title><link>https://example.com/</link><description>bar</description>\
+            <item><title>inky &amp; co</title><guid>inky</guid><description>sue &amp; co &lt; 3</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>&lt;b&gt;pinky&lt;/b&gt;</title><guid>pinky</guid><description>&lt;p&gt;pinky&lt;br&gt;&amp;nbsp;&lt;/p&gt;</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>blinky</title><guid>blinky</guid><description><![CDATA[<p>blinky <a href=\"https://example.com/?a=1&amp;b=2\">more</a><br/></p>]]></description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            <item><title>clyde</title><guid>clyde</guid><description>&lt;p&gt;clyde &lt;a href=\"https://example.com/?a=1&amp;b=2\"&gt;more&lt;/a&gt;</description><pubDate>Wed, 26 Jun 2024 20:46:00 +0200</pubDate></item>\
+            </channel></rss>";
+        let feed = super::from_bytes(src, "", false, super::DateFallback::Reject).unwrap();
+        assert_eq!(feed.title.r#type, TextType::Text);
+        assert_eq!(feed.title.value, "foo & bar");
+
+        assert_eq!(feed.entries[0].title.r#type, TextType::Text);
+        assert_eq!(feed.entries[0].title.value, "inky & co");
+        assert_eq!(feed.entries[0].summary.as_ref().unwrap().r#type, TextType::Text);
+        assert_eq!(feed.entries[0].summary.as_ref().unwrap().value, "sue & co < 3");
+
+        assert_eq!(feed.entries[1].title.r#type, TextType::Html);
+        assert_eq!(feed.entries[1].summary.as_ref().unwrap().r#type, TextType::Html);
+        assert_eq!(feed.entries[1].summary.as_ref().unwrap().value, "<p>pinky<br>&nbsp;</p>");
+
+        let summary = feed.entries[2].summary.as_ref().unwrap();
+        assert_eq!(summary.r#type, TextType::Xhtml);
+        assert!(summary.value.starts_with("<div xmlns=\"http://www.w3.org/1999/xhtml\"><p>blinky"));
+
+        assert_eq!(feed.entries[3].summary.as_ref().unwrap().r#type, TextType::Html);
+
+        // the xhtml summary reads back the same from the atom output.
+        let feed_again = Feed::read_from(feed.to_string().as_bytes()).unwrap();
+        assert_eq!(feed_again.entries[2].summary.as_ref().unwrap().r#type, TextType::Xhtml);
+        assert_eq!(feed_again.entries[0].summary.as_ref().unwrap().value, "sue & co < 3");
+    }
 }
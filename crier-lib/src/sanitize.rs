@@ -0,0 +1,193 @@
+use ammonia::Builder;
+use atom_syndication::Feed;
+use atom_syndication::Entry;
+use atom_syndication::Text;
+use atom_syndication::TextType;
+
+static DEFAULT_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "code", "dd", "del", "div", "dl", "dt", "em",
+    "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd",
+    "li", "ol", "p", "pre", "q", "s", "small", "span", "strong", "sub", "sup", "table", "tbody",
+    "td", "tfoot", "th", "thead", "tr", "u", "ul",
+];
+
+static DEFAULT_TAG_ATTRIBUTES: &[(&str, &str)] = &[
+    ("a", "href"), ("a", "hreflang"),
+    ("img", "src"), ("img", "alt"), ("img", "width"), ("img", "height"),
+    ("td", "colspan"), ("td", "rowspan"), ("th", "colspan"), ("th", "rowspan"),
+    ("blockquote", "cite"), ("q", "cite"), ("del", "cite"), ("ins", "cite"),
+];
+
+static DEFAULT_ATTRIBUTES: &[&str] = &[
+    "lang", "title",
+];
+
+static DEFAULT_URL_SCHEMES: &[&str] = &[
+    "http", "https", "mailto",
+];
+
+static LINK_REL: &str = "noopener noreferrer nofollow";
+
+/// Removes markup not in an allowlist of tags and attributes from entry summaries and content.
+///
+/// Anything not allowed is dropped, including scripts, event handler attributes, iframes and
+/// styles. Images sized one pixel or less, commonly used for tracking, are dropped as well.
+/// Plain text is left as it is.
+///
+/// The `rel` attribute of links is always set by the sanitizer, and can not be allowed.
+#[derive(Debug)]
+pub struct Sanitizer<'a> {
+    builder: Builder<'a>,
+}
+
+impl<'a> Sanitizer<'a> {
+    pub fn new() -> Sanitizer<'a> {
+        let mut o = Sanitizer{
+            builder: Builder::empty(),
+        };
+        o.builder.tags(DEFAULT_TAGS.iter().copied().collect());
+        o.builder.generic_attributes(DEFAULT_ATTRIBUTES.iter().copied().collect());
+        o.builder.url_schemes(DEFAULT_URL_SCHEMES.iter().copied().collect());
+        o.builder.link_rel(Some(LINK_REL));
+        for (tag, attr) in DEFAULT_TAG_ATTRIBUTES {
+            o = o.with_tag_attribute(tag, attr);
+        }
+        o
+    }
+
+    /// Replace the allowed tags.
+    pub fn with_tags(mut self, tags: &[&'a str]) -> Sanitizer<'a> {
+        self.builder.tags(tags.iter().copied().collect());
+        self
+    }
+
+    /// Allow an additional tag.
+    pub fn with_tag(mut self, tag: &'a str) -> Sanitizer<'a> {
+        self.builder.add_tags([tag]);
+        self
+    }
+
+    /// Allow an attribute on the given tag.
+    pub fn with_tag_attribute(mut self, tag: &'a str, attribute: &'a str) -> Sanitizer<'a> {
+        if tag == "a" && attribute == "rel" {
+            return self;
+        }
+        self.builder.add_tag_attributes(tag, [attribute]);
+        self
+    }
+
+    /// Replace the attributes allowed on all tags.
+    pub fn with_attributes(mut self, attributes: &[&'a str]) -> Sanitizer<'a> {
+        self.builder.generic_attributes(attributes.iter().copied().filter(|v| *v != "rel").collect());
+        self
+    }
+
+    /// Replace the url schemes allowed in links and image sources.
+    pub fn with_url_schemes(mut self, schemes: &[&'a str]) -> Sanitizer<'a> {
+        self.builder.url_schemes(schemes.iter().copied().collect());
+        self
+    }
+
+    /// Sanitize an html fragment.
+    pub fn clean(&self, s: &str) -> String {
+        self.builder.clean(strip_pixels(s).as_str()).to_string()
+    }
+
+    fn clean_text(&self, v: &mut Text) {
+        match v.r#type {
+            TextType::Html => {
+                v.value = self.clean(v.value.as_str());
+            },
+            // the cleaned markup is serialized as html, which is not necessarily well-formed xml.
+            TextType::Xhtml => {
+                v.value = self.clean(v.value.as_str());
+                v.r#type = TextType::Html;
+            },
+            TextType::Text => {},
+        };
+    }
+
+    /// Sanitize the summary and content of the entry.
+    pub fn apply_entry(&self, entry: &mut Entry) {
+        match &mut entry.summary {
+            Some(v) => {
+                self.clean_text(v);
+            },
+            None => {},
+        };
+        match &mut entry.content {
+            Some(v) => {
+                match v.content_type.as_deref() {
+                    Some("html") | Some("text/html") => {
+                        v.value = v.value.as_ref().map(|vv| self.clean(vv.as_str()));
+                    },
+                    // as with xhtml text, the cleaned markup is html.
+                    Some("xhtml") | Some("application/xhtml+xml") => {
+                        v.value = v.value.as_ref().map(|vv| self.clean(vv.as_str()));
+                        v.content_type = Some(String::from("html"));
+                    },
+                    _ => {},
+                };
+            },
+            None => {},
+        };
+    }
+
+    /// Sanitize the summaries and content of all entries of the feed.
+    pub fn apply(&self, feed: &mut Feed) {
+        for v in feed.entries.iter_mut() {
+            self.apply_entry(v);
+        }
+    }
+}
+
+impl<'a> Default for Sanitizer<'a> {
+    fn default() -> Sanitizer<'a> {
+        Sanitizer::new()
+    }
+}
+
+/// value of a pixel dimension attribute, if the tag has it.
+fn dimension(tag: &str, name: &str) -> Option<u32> {
+    let mut rest = tag;
+
+    while let Some(i) = rest.find(name) {
+        let before = rest[..i].chars().last();
+        rest = &rest[i + name.len()..];
+        if !before.map(|c| c.is_whitespace()).unwrap_or(false) {
+            continue;
+        }
+        let v = rest.trim_start();
+        let v = match v.strip_prefix('=') {
+            Some(v) => v.trim_start().trim_start_matches(|c| c == '"' || c == '\''),
+            None => continue,
+        };
+        let digits: String = v.chars().take_while(|c| c.is_ascii_digit()).collect();
+        return digits.parse().ok();
+    }
+    None
+}
+
+/// drop img tags with a width or height of at most one pixel.
+fn strip_pixels(s: &str) -> String {
+    let mut r: String;
+    let mut rest: &str;
+    let lower = s.to_ascii_lowercase();
+
+    r = String::new();
+    rest = s;
+    while let Some(i) = lower[s.len() - rest.len()..].find("<img") {
+        let end = match rest[i..].find('>') {
+            Some(v) => i + v + 1,
+            None => break,
+        };
+        let tag = &lower[s.len() - rest.len() + i..s.len() - rest.len() + end];
+        r.push_str(&rest[..i]);
+        if dimension(tag, "width").map(|v| v > 1).unwrap_or(true) && dimension(tag, "height").map(|v| v > 1).unwrap_or(true) {
+            r.push_str(&rest[i..end]);
+        }
+        rest = &rest[end..];
+    }
+    r.push_str(rest);
+    r
+}
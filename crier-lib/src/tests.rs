@@ -1,29 +1,66 @@
 use std::clone::Clone;
 use std::fs::File;
-use std::io::{SeekFrom, Seek, Read};
+use std::io::{SeekFrom, Seek, Read, Write};
 use std::str;
 
 use mediatype::MediaTypeBuf;
 use chrono::DateTime;
+use chrono::Local;
+use chrono::TimeDelta;
 use tempfile::NamedTempFile;
 use tempfile::tempdir;
 use atom_syndication::Entry as OutEntry;
 use atom_syndication::Feed as OutFeed;
 use atom_syndication::Person;
 use atom_syndication::Text;
+use atom_syndication::Link;
+use atom_syndication::Category;
+use rss::Channel;
 use quick_xml::Reader as XMLReader;
 use quick_xml::events::Event as XMLEvent;
 
 use crate::Sequencer;
+use crate::opml;
+use crate::ErrorKind;
+use crate::SequencerOrder;
+use crate::SequencerKey;
+use crate::DateFallback;
+use crate::CachedEntry;
 use crate::io::FeedGet;
+use crate::io::FeedPut;
+use crate::io::FeedMethod;
+use crate::io::FeedState;
+use crate::io::state::StateStore;
 use crate::meta::FeedMetadata;
 use crate::Feed;
 use crate::Entry;
 use crate::io::fs::FsCache;
 use crate::mem::MemCache;
+use crate::cache::Cache;
+use crate::cache::retention;
+use crate::cache::retention::MaxAge;
+use crate::cache::retention::MaxEntries;
+use crate::cache::retention::MaxBytes;
 
 #[cfg(feature = "fs")]
 use crate::io::fs::FsFeed;
+#[cfg(feature = "fs")]
+use crate::io::fs::FsFeedReader;
+
+#[cfg(feature = "sqlite")]
+use crate::io::sqlite::SqliteCache;
+
+#[cfg(feature = "sanitize")]
+use crate::sanitize::Sanitizer;
+
+#[cfg(feature = "http")]
+use std::net::TcpListener;
+#[cfg(feature = "http")]
+use std::io::{BufRead, BufReader};
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use crate::io::http::HttpFeed;
 
 
 fn check_xml_title(xml: Vec<u8>, title: &str) {
@@ -60,7 +97,7 @@ fn check_xml_title(xml: Vec<u8>, title: &str) {
 #[test]
 fn test_entry_guard() {
     let mut r: bool;
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     let mut src = Entry::default();
     let mut s: String;
 
@@ -69,25 +106,333 @@ fn test_entry_guard() {
     src.title = Text::plain(s);
         
     src.published = Some(DateTime::parse_from_rfc3339("2024-06-25T20:46:00+02:00").unwrap().into());
-    r = seq.add(src);
+    r = seq.add(src.clone());
     assert!(r);
 
+    r = seq.add(src);
+    assert!(!r);
+
+    // same date, edited content
     let mut src_two = Entry::default();
     src_two.id = String::from("foo");
     s = String::from("pinky");
     src_two.title = Text::plain(s);
     src_two.published = Some(DateTime::parse_from_rfc3339("2024-06-25T20:46:00+02:00").unwrap().into());
+    src_two.updated = DateTime::parse_from_rfc3339("2024-06-25T20:46:00+02:00").unwrap();
     r = seq.add(src_two);
-    assert!(!r);
+    assert!(r);
 
+    // older revision
     let mut src_three = Entry::default();
     src_three.id = String::from("foo");
     s = String::from("blinky");
     src_three.title = Text::plain(s);
-
     src_three.published = Some(DateTime::parse_from_rfc3339("2024-06-25T20:46:00+03:00").unwrap().into());
     r = seq.add(src_three);
+    assert!(!r);
+
+    // bumped date
+    let mut src_four = Entry::default();
+    src_four.id = String::from("foo");
+    s = String::from("clyde");
+    src_four.title = Text::plain(s);
+    src_four.published = Some(DateTime::parse_from_rfc3339("2024-06-25T20:46:00+02:00").unwrap().into());
+    src_four.updated = DateTime::parse_from_rfc3339("2024-06-26T20:46:00+02:00").unwrap();
+    r = seq.add(src_four);
     assert!(r);
+
+    check_xml_title(seq.next().unwrap(), "clyde");
+    assert!(seq.next().is_none());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_entry_source() {
+    let mut r: bool;
+    let mut cache: FsCache;
+    let b: String;
+    let d = tempdir().unwrap();
+    cache = FsCache::new(d.path().to_path_buf());
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    let mut src = Entry::default();
+
+    src.id = String::from("foo");
+    src.title = Text::plain(String::from("clyde"));
+    r = seq.add_with_source(src.clone(), "bar");
+    assert!(r);
+    r = seq.add_with_source(src.clone(), "baz");
+    assert!(r);
+    r = seq.add_with_source(src.clone(), "bar");
+    assert!(!r);
+
+    src.updated = DateTime::parse_from_rfc3339("2024-06-26T20:46:00+02:00").unwrap();
+    src.title = Text::plain(String::from("sue"));
+    r = seq.add_with_source(src, "bar");
+    assert!(r);
+    assert_eq!(seq.by_ref().count(), 2);
+
+    cache = FsCache::new(d.path().to_path_buf());
+    b = String::from_utf8(cache.get("3:bar foo").unwrap().unwrap()).unwrap();
+    assert!(b.contains("sue"));
+    assert!(!b.contains("clyde"));
+}
+
+#[test]
+fn test_entry_source_key() {
+    let mut cache = MemCache::new();
+    let mut src = Entry::default();
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+
+    // the same feed from two sources is kept twice.
+    let mut feed = OutFeed::default();
+    src.id = String::from("foo");
+    feed.entries.push(src.clone());
+    assert_eq!(seq.add_from_source(feed.clone(), "inky"), 1);
+    assert_eq!(seq.add_from_source(feed, "pinky"), 1);
+
+    // sources containing spaces do not collide with ids containing them.
+    src.id = String::from("b c");
+    assert!(seq.add_with_source(src.clone(), "a"));
+    src.id = String::from("c");
+    assert!(seq.add_with_source(src.clone(), "a b"));
+    assert_eq!(seq.by_ref().count(), 4);
+
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.restore_cache().unwrap(), 4);
+    assert!(!seq.add_with_source(src, "a b"));
+}
+
+#[test]
+fn test_entry_fallback_date_kept() {
+    let mut cache = MemCache::new();
+    let src = b"<rss version=\"2.0\"><channel><title>foo</title><link>https://example.com/</link><description>bar</description>\
+        <item><title>inky</title><guid>inky</guid><description>xyzzy</description></item>\
+        </channel></rss>";
+
+    let feed = crate::rss::from_bytes(src, "", false, DateFallback::Fetched).unwrap();
+    let first = feed.entries[0].updated;
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert!(seq.add_with_source(feed.entries[0].clone(), "foo"));
+
+    // a later read of the same item is not a new revision.
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.restore_cache().unwrap(), 1);
+    let mut feed = crate::rss::from_bytes(src, "", false, DateFallback::Fetched).unwrap();
+    feed.entries[0].updated = first + TimeDelta::seconds(1);
+    assert!(!seq.add_with_source(feed.entries[0].clone(), "foo"));
+
+    // nor does a changed item get a new date.
+    feed.entries[0].set_summary(Some(Text::plain("plugh")));
+    assert!(seq.add_with_source(feed.entries[0].clone(), "foo"));
+    let entry = seq.to_feed().unwrap().entries[0].clone();
+    assert_eq!(entry.updated, first);
+    assert_eq!(entry.summary.unwrap().value, "plugh");
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_cache_restore() {
+    let mut cache: FsCache;
+    let d = tempdir().unwrap();
+    let fs = FsFeed{};
+    let feed = fs.get("testdata/test.atom.xml", None).unwrap();
+    let mut src = Entry::default();
+
+    src.id = String::from("foo/bar");
+    src.title = Text::plain(String::from("clyde"));
+    cache = FsCache::new(d.path().to_path_buf());
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    seq.add_from(feed.clone());
+    seq.add(src.clone());
+    assert_eq!(seq.by_ref().count(), 17);
+    seq.sync().unwrap();
+
+    cache = FsCache::new(d.path().to_path_buf());
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.restore_cache().unwrap(), 17);
+
+    // restored entries are recognized as already known.
+    assert!(!seq.add(src));
+    assert_eq!(seq.add_from(feed), 16);
+    assert_eq!(seq.by_ref().count(), 17);
+}
+
+fn check_cache(cache: &mut dyn Cache) {
+    let mut ids: Vec<String>;
+
+    assert!(cache.list().unwrap().is_empty());
+    assert!(cache.get("foo/bar").unwrap().is_none());
+
+    cache.open(String::from("foo/bar")).write_all(b"inky").unwrap();
+    cache.close(String::from("foo/bar"));
+    cache.open(String::from("baz")).write_all(b"pinky").unwrap();
+    cache.close(String::from("baz"));
+    assert!(cache.exists("foo/bar"));
+    assert!(!cache.exists("foo"));
+    assert_eq!(cache.get("foo/bar").unwrap().unwrap(), b"inky");
+    assert!(cache.modified("baz").is_some());
+
+    // rewriting an item keeps the time it was first seen.
+    let seen = cache.seen("foo/bar").unwrap();
+    cache.open(String::from("foo/bar")).write_all(b"clyde").unwrap();
+    cache.close(String::from("foo/bar"));
+    assert_eq!(cache.seen("foo/bar").unwrap(), seen);
+    assert_eq!(cache.get("foo/bar").unwrap().unwrap(), b"clyde");
+
+    ids = cache.list().unwrap();
+    ids.sort();
+    assert_eq!(ids, vec!("baz", "foo/bar"));
+
+    assert!(cache.remove("foo/bar").unwrap());
+    assert!(!cache.remove("foo/bar").unwrap());
+    assert!(!cache.exists("foo/bar"));
+    assert_eq!(cache.list().unwrap(), vec!("baz"));
+}
+
+#[test]
+fn test_cache_mem() {
+    let mut cache = MemCache::new();
+    check_cache(&mut cache);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_cache_fs() {
+    let d = tempdir().unwrap();
+    let mut cache = FsCache::new(d.path().to_path_buf());
+    check_cache(&mut cache);
+
+    // ids differing only in characters that are unsafe or lost in file names do not collide.
+    let long_id = format!("https://example.com/{}", "x".repeat(1024));
+    for v in ["foo:bar", "foo?bar", "Foo?bar", "foo\tbar", "foo\nbar", long_id.as_str()] {
+        cache.open(String::from(v)).write_all(v.as_bytes()).unwrap();
+        cache.close(String::from(v));
+    }
+
+    let seen = cache.seen("baz").unwrap();
+
+    // new ids are written to the index on sync.
+    assert!(!d.path().join("index").exists());
+    cache.sync().unwrap();
+    let index = std::fs::read_to_string(d.path().join("index")).unwrap();
+    assert_eq!(index.lines().count(), 7);
+
+    cache = FsCache::new(d.path().to_path_buf());
+    assert_eq!(cache.list().unwrap().len(), 7);
+    assert_eq!(cache.seen("baz").unwrap(), seen);
+    for v in ["foo:bar", "foo?bar", "Foo?bar", "foo\tbar", "foo\nbar", long_id.as_str()] {
+        assert_eq!(cache.get(v).unwrap().unwrap(), v.as_bytes());
+    }
+    assert!(cache.remove("foo?bar").unwrap());
+    cache = FsCache::new(d.path().to_path_buf());
+    assert_eq!(cache.list().unwrap().len(), 6);
+    assert!(cache.exists("Foo?bar"));
+
+    // removals are written to the index on sync.
+    assert!(cache.remove("foo:bar").unwrap());
+    assert!(cache.remove("Foo?bar").unwrap());
+    cache.sync().unwrap();
+    let index = std::fs::read_to_string(d.path().join("index")).unwrap();
+    assert_eq!(index.lines().count(), 4);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_cache_fs_migrate() {
+    let d = tempdir().unwrap();
+    let mut src = Entry::default();
+    src.id = String::from("foo/bar");
+    src.title = Text::plain(String::from("clyde"));
+    std::fs::write(d.path().join("inky foo%2Fbar"), src.write_to(Vec::new()).unwrap()).unwrap();
+    std::fs::write(d.path().join("state"), "not an entry").unwrap();
+
+    let mut cache = FsCache::new(d.path().to_path_buf());
+    assert_eq!(cache.list().unwrap(), vec!("4:inky foo/bar"));
+    assert!(cache.seen("4:inky foo/bar").is_some());
+    assert!(!d.path().join("inky foo%2Fbar").exists());
+    assert!(d.path().join("state").exists());
+
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.restore_cache().unwrap(), 1);
+    assert!(!seq.add_with_source(src.clone(), "inky"));
+
+    // the directory is only scanned until the migration is done.
+    std::fs::write(d.path().join("blinky foo%2Fbar"), src.write_to(Vec::new()).unwrap()).unwrap();
+    let cache = FsCache::new(d.path().to_path_buf());
+    assert_eq!(cache.list().unwrap(), vec!("4:inky foo/bar"));
+    assert!(d.path().join("blinky foo%2Fbar").exists());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_cache_fs_error() {
+    let f = NamedTempFile::new().unwrap();
+    let mut cache = FsCache::new(f.path().join("cache"));
+    assert!(cache.open(String::from("foo")).write_all(b"inky").is_err());
+    cache.close(String::from("foo"));
+    assert!(!cache.exists("foo"));
+}
+
+#[test]
+#[cfg(all(feature = "sqlite", feature = "fs"))]
+fn test_cache_sqlite() {
+    let mut cache: SqliteCache;
+    let d = tempdir().unwrap();
+    let fp = d.path().join("cache.db");
+    let fs = FsFeed{};
+    let feed = fs.get("testdata/test.atom.xml", None).unwrap();
+
+    cache = SqliteCache::new(fp.as_path()).unwrap();
+    check_cache(&mut cache);
+    assert!(cache.remove("baz").unwrap());
+
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.add_from(feed.clone()), 16);
+    assert_eq!(seq.by_ref().count(), 16);
+    seq.sync().unwrap();
+
+    cache = SqliteCache::new(fp.as_path()).unwrap();
+    assert_eq!(cache.list().unwrap().len(), 16);
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    assert_eq!(seq.restore_cache().unwrap(), 16);
+    assert!(!seq.add(feed.entries[0].clone()));
+}
+
+#[test]
+fn test_cache_gc() {
+    let mut r: Vec<String>;
+    let mut cache = MemCache::new();
+    let now = Local::now().fixed_offset();
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+
+    for i in 0..5 {
+        let date = (now - TimeDelta::days(i * 10)).to_rfc3339();
+        seq.add(bounds_entry(format!("foo{}", i).as_str(), "inky", date.as_str()));
+    }
+    seq.add(bounds_entry("bar", "pinky pinky pinky pinky pinky pinky pinky", now.to_rfc3339().as_str()));
+
+    // an age beyond the representable dates evicts nothing.
+    let max_age = MaxAge::new(TimeDelta::MAX);
+    r = seq.gc(&[&max_age]).unwrap();
+    assert!(r.is_empty());
+
+    let max_age = MaxAge::new(TimeDelta::days(35));
+    r = seq.gc(&[&max_age]).unwrap();
+    assert_eq!(r, vec!("foo4"));
+
+    let max_entries = MaxEntries::new(4);
+    r = seq.gc(&[&max_age, &max_entries]).unwrap();
+    assert_eq!(r, vec!("foo3"));
+
+    let sizes = retention::items(&cache).unwrap();
+    assert_eq!(sizes.len(), 4);
+    let mut seq = Sequencer::new(vec!()).with_cache(&mut cache);
+    seq.restore_cache().unwrap();
+    let max_bytes = MaxBytes::new(sizes[0].size + sizes[1].size + sizes[2].size);
+    r = seq.gc(&[&max_bytes]).unwrap();
+    assert_eq!(r, vec!("foo2"));
+    assert_eq!(seq.by_ref().count(), 3);
+    assert!(!cache.exists("foo2"));
 }
 
 #[test]
@@ -96,7 +441,7 @@ fn test_feed_get() {
     let r: bool;
     let fs = FsFeed{};
     let feed = fs.get("testdata/test.atom.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     //r = seq.add(feed.entries.get(0).unwrap().clone()); 
     //assert!(r);
 }
@@ -107,7 +452,7 @@ fn test_feed_all() {
     let r: i64;
     let fs = FsFeed{};
     let feed = fs.get("testdata/test.atom.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     r = seq.add_from(feed); 
     assert_eq!(r, 16);
 }
@@ -118,7 +463,7 @@ fn test_feed_mix() {
     let mut r: i64;
     let fs = FsFeed{};
     let mut feed = fs.get("testdata/test.atom.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     r = seq.add_from(feed); 
     assert_eq!(r, 16);
     feed = fs.get("testdata/test2.xml", None).unwrap();
@@ -128,6 +473,290 @@ fn test_feed_mix() {
     assert_eq!(seq.count(), 26);
 }
 
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_provenance() {
+    let fs = FsFeed{};
+    let feed = fs.get("testdata/test.atom.xml", None).unwrap();
+    let mut seq = Sequencer::new(vec!());
+    seq.add_from(feed.clone());
+    let out = seq.to_feed().unwrap();
+    assert!(out.entries[0].source.is_none());
+
+    let mut seq = Sequencer::new(vec!());
+    seq.set_provenance(true);
+    seq.add_from(feed);
+    let out = seq.to_feed().unwrap();
+    assert_eq!(out.entries.len(), 16);
+    for v in out.entries.iter() {
+        let src = v.source.as_ref().unwrap();
+        assert_eq!(src.id, "/");
+        assert_eq!(src.title.value, "man bytes gnu");
+        assert_eq!(src.links.len(), 2);
+        assert_eq!(src.updated, DateTime::parse_from_rfc3339("2024-07-07T21:03:40+02:00").unwrap());
+    }
+}
+
+#[test]
+fn test_feed_rss_out() {
+    let mut b: Vec<u8>;
+    let mut entry: Entry;
+    let mut link = Link::default();
+    let mut enclosure = Link::default();
+    let mut cat = Category::default();
+    let mut seq = Sequencer::new(vec!());
+
+    entry = bounds_entry("https://example.com/foo", "inky", "2024-06-25T20:46:00+02:00");
+    entry.summary = Some(Text::plain("pinky"));
+    link.href = String::from("https://example.com/foo.html");
+    entry.links.push(link);
+    enclosure.href = String::from("https://example.com/foo.ogg");
+    enclosure.rel = String::from("enclosure");
+    enclosure.mime_type = Some(String::from("audio/ogg"));
+    enclosure.length = Some(String::from("42"));
+    entry.links.push(enclosure);
+    cat.term = String::from("blinky");
+    cat.scheme = Some(String::from("https://example.com/tags"));
+    entry.categories.push(cat);
+    entry.authors.push(Person{
+        name: String::from("Clyde"),
+        email: Some(String::from("clyde@example.com")),
+        uri: None,
+    });
+    seq.add(entry);
+    seq.add(bounds_entry("tag:example.com,2024:bar", "sue", "2024-06-26T20:46:00+02:00"));
+
+    b = Vec::new();
+    assert_eq!(seq.write_rss_to(&mut b).unwrap(), 2);
+    let channel = Channel::read_from(b.as_slice()).unwrap();
+    assert_eq!(channel.items.len(), 2);
+
+    let item = &channel.items[0];
+    assert_eq!(item.title.as_ref().unwrap(), "inky");
+    assert_eq!(item.link.as_ref().unwrap(), "https://example.com/foo.html");
+    assert_eq!(item.description.as_ref().unwrap(), "pinky");
+    assert_eq!(item.pub_date.as_ref().unwrap(), "Tue, 25 Jun 2024 20:46:00 +0200");
+    assert_eq!(item.author.as_ref().unwrap(), "clyde@example.com (Clyde)");
+    assert_eq!(item.categories[0].name, "blinky");
+    assert_eq!(item.categories[0].domain.as_ref().unwrap(), "https://example.com/tags");
+    let enclosure = item.enclosure.as_ref().unwrap();
+    assert_eq!(enclosure.url, "https://example.com/foo.ogg");
+    assert_eq!(enclosure.length, "42");
+    assert_eq!(enclosure.mime_type, "audio/ogg");
+    assert!(item.guid.as_ref().unwrap().is_permalink());
+
+    let item = &channel.items[1];
+    assert_eq!(item.guid.as_ref().unwrap().value, "tag:example.com,2024:bar");
+    assert!(!item.guid.as_ref().unwrap().is_permalink());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_rdf() {
+    let fs = FsFeed{};
+
+    let feed = fs.get("testdata/test.rdf.xml", None).unwrap();
+    assert_eq!(feed.id, "https://example.com/index.rdf");
+    assert_eq!(feed.title.value, "inky feed");
+    assert_eq!(feed.authors[0].name, "Inky");
+    assert_eq!(feed.rights.as_ref().unwrap().value, "Copyright inky");
+    assert_eq!(feed.entries.len(), 2);
+
+    let entry = &feed.entries[0];
+    assert_eq!(entry.id, "https://example.com/entry/1");
+    assert_eq!(entry.title.value, "pinky");
+    assert_eq!(entry.authors[0].name, "Pinky");
+    assert_eq!(entry.categories.len(), 2);
+    assert_eq!(entry.categories[1].term, "clyde");
+    assert_eq!(entry.published, Some(DateTime::parse_from_rfc3339("2024-06-25T20:46:00+09:00").unwrap()));
+    assert_eq!(feed.entries[1].id, "https://example.com/entry/2");
+    assert_eq!(feed.entries[1].summary.as_ref().unwrap().value, "sue & co");
+
+    let feed = fs.get("testdata/test.rss.xml", None).unwrap();
+    assert_eq!(feed.id, "https://holbrook.no");
+    assert_eq!(feed.entries.len(), 1);
+    assert!(feed.entries[0].id.starts_with("https://holbrook.no/share/releases/bluto/"));
+}
+
+#[test]
+fn test_feed_rdf_empty_item() {
+    let src = b"<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\" xmlns=\"http://purl.org/rss/1.0/\">\
+        <channel rdf:about=\"https://example.com/index.rdf\"><title>inky feed</title><link>https://example.com/</link><description>ghosts</description></channel>\
+        <item rdf:about=\"https://example.com/entry/1\"/>\
+        </rdf:RDF>";
+
+    // the item is read, and fails for lack of date and content instead of being left out.
+    let e = crate::rss::from_bytes(src, "", false, DateFallback::Fetched).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IncompleteError);
+    assert_eq!(e.entry().unwrap(), "https://example.com/entry/1");
+
+    let feed = crate::rss::from_bytes(src, "", true, DateFallback::Fetched).unwrap();
+    assert_eq!(feed.entries.len(), 0);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_json() {
+    let mut b: Vec<u8>;
+    let fs = FsFeed{};
+
+    let e = fs.get("testdata/test.json", None).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IncompleteError);
+    assert_eq!(e.entry().unwrap(), "3");
+
+    b = Vec::new();
+    File::open("testdata/test.json").unwrap().read_to_end(&mut b).unwrap();
+    let feed = crate::rss::from_bytes(b.as_slice(), "", true, DateFallback::Reject).unwrap();
+    assert_eq!(feed.id, "https://example.com/feed.json");
+    assert_eq!(feed.entries.len(), 2);
+    assert_eq!(feed.updated, DateTime::parse_from_rfc3339("2024-06-27T20:46:00+02:00").unwrap());
+
+    let undated = crate::rss::from_bytes(b.as_slice(), "", false, DateFallback::Fetched).unwrap();
+    assert_eq!(undated.entries.len(), 3);
+    assert_eq!(undated.entries[2].categories[0].term, "fetched");
+    assert_eq!(undated.entries[2].categories[0].scheme.as_ref().unwrap(), crate::DATE_FALLBACK_SCHEME);
+
+    let entry = &feed.entries[0];
+    assert_eq!(entry.title.value, "pinky");
+    assert_eq!(entry.links[0].href, "https://example.com/pinky.html");
+    assert_eq!(entry.links[1].rel, "enclosure");
+    assert_eq!(entry.links[1].length.as_ref().unwrap(), "42");
+    assert_eq!(entry.updated, DateTime::parse_from_rfc3339("2024-06-26T20:46:00+02:00").unwrap());
+    assert_eq!(entry.categories.len(), 2);
+    assert_eq!(feed.entries[1].authors[0].name, "Sue");
+
+    let mut seq = Sequencer::new(vec!());
+    seq.add_from(feed);
+    b = Vec::new();
+    assert_eq!(seq.write_json_to(&mut b).unwrap(), 2);
+    let feed = crate::rss::from_bytes(b.as_slice(), "", false, DateFallback::Reject).unwrap();
+    assert_eq!(feed.entries.len(), 2);
+    assert_eq!(feed.entries[0].content.as_ref().unwrap().value.as_ref().unwrap(), "<p>pinky <em>pinky</em></p>");
+    assert_eq!(feed.entries[0].links[1].mime_type.as_ref().unwrap(), "audio/ogg");
+    assert_eq!(feed.entries[1].content.as_ref().unwrap().content_type.as_ref().unwrap(), "text");
+    assert_eq!(feed.entries[1].published, Some(DateTime::parse_from_rfc3339("2024-06-27T20:46:00+02:00").unwrap()));
+}
+
+#[test]
+fn test_feed_write_text_type() {
+    let mut b: Vec<u8>;
+    let mut seq = Sequencer::new(vec!());
+    let mut entry = bounds_entry("tag:example.com,2024:inky", "", "2024-06-25T20:46:00+02:00");
+
+    entry.title = Text::html("<b>inky</b> &amp; co&nbsp;!");
+    entry.summary = Some(Text::xhtml("<div xmlns=\"http://www.w3.org/1999/xhtml\"><p>pinky</p></div>"));
+    let mut content = atom_syndication::Content::default();
+    content.set_content_type(Some(String::from("xhtml")));
+    content.set_value(Some(String::from("<div xmlns=\"http://www.w3.org/1999/xhtml\"><p>blinky</p></div>")));
+    entry.set_content(Some(content));
+    seq.add(entry);
+
+    b = Vec::new();
+    seq.write_rss_to(&mut b).unwrap();
+    let channel = Channel::read_from(b.as_slice()).unwrap();
+    let item = &channel.items[0];
+    assert_eq!(item.title.as_ref().unwrap(), "inky & co\u{a0}!");
+    assert_eq!(item.description.as_ref().unwrap(), "<p>pinky</p>");
+    assert_eq!(item.content.as_ref().unwrap(), "<p>blinky</p>");
+
+    b = Vec::new();
+    seq.write_json_to(&mut b).unwrap();
+    let v: serde_json::Value = serde_json::from_slice(b.as_slice()).unwrap();
+    assert_eq!(v["items"][0]["title"], "inky & co\u{a0}!");
+    assert_eq!(v["items"][0]["summary"], "pinky");
+    assert_eq!(v["items"][0]["content_html"], "<p>blinky</p>");
+}
+
+#[test]
+#[cfg(feature = "sanitize")]
+fn test_sanitize() {
+    let mut feed: OutFeed;
+    let mut entry = OutEntry::default();
+    let sanitizer = Sanitizer::new();
+
+    entry.set_summary(Some(Text::html("<p onclick=\"evil()\">inky <script>evil()</script><img src=\"https://t.example.com/p.gif\" width=\"1\" height=\"1\"><img src=\"https://example.com/inky.png\" alt=\"inky\"></p>")));
+    let mut content = atom_syndication::Content::default();
+    content.set_content_type(Some(String::from("text/html")));
+    content.set_value(Some(String::from("<iframe src=\"https://example.com/\"></iframe><a href=\"javascript:evil()\">pinky</a> <a href=\"https://example.com/\" style=\"color: red\">blinky</a>")));
+    entry.set_content(Some(content));
+    feed = OutFeed::default();
+    feed.entries.push(entry);
+    feed.entries.push(OutEntry::default());
+    feed.entries[1].set_summary(Some(Text::plain("<b>clyde</b>")));
+
+    sanitizer.apply(&mut feed);
+    assert_eq!(feed.entries[0].summary.as_ref().unwrap().value, "<p>inky <img src=\"https://example.com/inky.png\" alt=\"inky\"></p>");
+    assert_eq!(feed.entries[0].content.as_ref().unwrap().value.as_ref().unwrap(), "<a rel=\"noopener noreferrer nofollow\">pinky</a> <a href=\"https://example.com/\" rel=\"noopener noreferrer nofollow\">blinky</a>");
+    assert_eq!(feed.entries[1].summary.as_ref().unwrap().value, "<b>clyde</b>");
+
+    let sanitizer = Sanitizer::new().with_tags(&["p"]);
+    assert_eq!(sanitizer.clean("<p><b>sue</b></p>"), "<p>sue</p>");
+
+    // rel is set by the sanitizer, whether or not it is allowed.
+    let sanitizer = Sanitizer::new().with_tag_attribute("a", "rel").with_attributes(&["rel", "title"]);
+    assert_eq!(sanitizer.clean("<a href=\"https://example.com/\" rel=\"me\">sue</a>"), "<a href=\"https://example.com/\" rel=\"noopener noreferrer nofollow\">sue</a>");
+
+    let mut entry = OutEntry::default();
+    let mut content = atom_syndication::Content::default();
+    content.set_content_type(Some(String::from("xhtml")));
+    content.set_value(Some(String::from("<div xmlns=\"http://www.w3.org/1999/xhtml\"><p>sue<br/><script>evil()</script></p></div>")));
+    entry.set_content(Some(content));
+    sanitizer.apply_entry(&mut entry);
+    let content = entry.content.unwrap();
+    assert_eq!(content.content_type.unwrap(), "html");
+    assert_eq!(content.value.unwrap(), "<div><p>sue<br></p></div>");
+}
+
+#[test]
+fn test_date_parse() {
+    let mut s: String;
+    let mut failed: Vec<String>;
+
+    s = String::new();
+    File::open("testdata/dates.txt").unwrap().read_to_string(&mut s).unwrap();
+    failed = Vec::new();
+    for v in s.lines() {
+        if v.starts_with('#') {
+            continue;
+        }
+        let (ipt, expect) = v.split_once('\t').unwrap();
+        let r = match crate::date::parse(ipt) {
+            Ok(r) => {
+                r.to_rfc3339()
+            },
+            Err(_e) => {
+                String::from("-")
+            },
+        };
+        if r != expect {
+            failed.push(format!("'{}': expected {}, got {}", ipt, expect, r));
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+#[test]
+fn test_date_atom() {
+    let src = b"<?xml version=\"1.0\"?><feed xmlns=\"http://www.w3.org/2005/Atom\"><title>foo</title><id>urn:foo</id>\
+        <updated>Dienstag, 25. Juni 2024</updated>\
+        <entry><title>inky</title><id>urn:foo:inky</id><updated>2024-06-25 20:46 CEST</updated><published>25 juin 2024</published></entry>\
+        </feed>";
+    let feed = crate::rss::from_bytes(src, "", false, DateFallback::Reject).unwrap();
+    assert_eq!(feed.updated.to_rfc3339(), "2024-06-25T00:00:00+00:00");
+    assert_eq!(feed.entries[0].updated.to_rfc3339(), "2024-06-25T20:46:00+02:00");
+    assert_eq!(feed.entries[0].published.unwrap().to_rfc3339(), "2024-06-25T00:00:00+00:00");
+    assert_eq!(feed.entries[0].title.value, "inky");
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_allow_entry_fail() {
+    let fs = FsFeedReader::new().with_allow_entry_fail(true);
+
+    let feed = fs.get("testdata/test.json", None).unwrap();
+    assert_eq!(feed.entries.len(), 2);
+}
+
 #[test]
 #[cfg(feature = "fs")]
 fn test_feed_write() {
@@ -137,7 +766,7 @@ fn test_feed_write() {
     let mut fr: File;
 
     let feed = fs.get("testdata/test.atom.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     seq.add_from(feed); 
     f = NamedTempFile::new().unwrap();
     fr = f.reopen().unwrap();
@@ -146,6 +775,30 @@ fn test_feed_write() {
     assert_eq!(fr.metadata().unwrap().len(), 520327);
 }
 
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_put() {
+    let fs = FsFeed{};
+    let d = tempdir().unwrap();
+    let fp = d.path().join("out.xml");
+    let s = fp.to_str().unwrap();
+
+    let feed = fs.get("testdata/test.atom.xml", None).unwrap();
+    let mut seq = Sequencer::new(vec!());
+    seq.add_from(feed);
+    let feed = seq.to_feed().unwrap();
+
+    fs.put(&feed, s, Some(FeedMethod::Create)).unwrap();
+    assert!(fs.put(&feed, s, Some(FeedMethod::Create)).is_err());
+    assert!(fs.put(&feed, s, Some(FeedMethod::Read)).is_err());
+    fs.put(&feed, s, Some(FeedMethod::Update)).unwrap();
+    fs.put(&feed, s, None).unwrap();
+
+    let feed = fs.get(s, None).unwrap();
+    assert_eq!(feed.entries.len(), 16);
+    assert_eq!(d.path().read_dir().unwrap().count(), 1);
+}
+
 #[test]
 #[cfg(feature = "fs")]
 fn test_feed_write_extcache() {
@@ -159,7 +812,7 @@ fn test_feed_write_extcache() {
     cache = FsCache::new(d.into_path());
         
     let feed = fs.get("testdata/test.atom.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     seq = seq.with_cache(&mut cache);
 
     seq.add_from(feed);
@@ -171,10 +824,102 @@ fn test_feed_write_extcache() {
     assert_eq!(fr.metadata().unwrap().len(), 520327);
 }
 
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_state() {
+    let fs = FsFeed{};
+    let mut state = FeedState::default();
+
+    let feed = fs.get_state("testdata/test.atom.xml", Some(FeedMethod::Update), &mut state).unwrap();
+    assert_eq!(feed.unwrap().entries.len(), 16);
+    assert!(state.mtime.is_some());
+    assert!(state.digest.is_some());
+
+    let feed = fs.get_state("testdata/test.atom.xml", Some(FeedMethod::Update), &mut state).unwrap();
+    assert!(feed.is_none());
+
+    // a file touched without changing still has its new time recorded.
+    let mtime = state.mtime;
+    state.mtime = Some(1);
+    let feed = fs.get_state("testdata/test.atom.xml", Some(FeedMethod::Update), &mut state).unwrap();
+    assert!(feed.is_none());
+    assert_eq!(state.mtime, mtime);
+
+    let feed = fs.get_state("testdata/test.atom.xml", Some(FeedMethod::Read), &mut state).unwrap();
+    assert!(feed.is_some());
+
+    // nothing is recorded for content that can not be read.
+    let mut f = NamedTempFile::new().unwrap();
+    f.write_all(b"<rss>").unwrap();
+    let mut state = FeedState::default();
+    fs.get_state(f.path().to_str().unwrap(), Some(FeedMethod::Update), &mut state).unwrap_err();
+    assert_eq!(state, FeedState::default());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_feed_state_store() {
+    let fs = FsFeed{};
+    let d = tempdir().unwrap();
+    let mut state = FeedState::default();
+    let mut store = StateStore::new(d.path().to_path_buf()).unwrap();
+    assert_eq!(store.get("testdata/test.atom.xml"), state);
+
+    let feed = fs.get_state("testdata/test.atom.xml", Some(FeedMethod::Update), &mut state).unwrap().unwrap();
+    store.set("testdata/test.atom.xml", state.clone(), &feed).unwrap();
+    store.save().unwrap();
+
+    let mut store = StateStore::new(d.path().to_path_buf()).unwrap();
+    assert_eq!(store.get("testdata/test.atom.xml"), state);
+    assert_eq!(store.feed("testdata/test.atom.xml").unwrap().entries.len(), 16);
+    assert!(store.feed("testdata/test2.xml").is_none());
+
+    state.mtime = Some(1);
+    store.update("testdata/test.atom.xml", state.clone());
+    store.save().unwrap();
+    let store = StateStore::new(d.path().to_path_buf()).unwrap();
+    assert_eq!(store.get("testdata/test.atom.xml"), state);
+    assert_eq!(store.feed("testdata/test.atom.xml").unwrap().entries.len(), 16);
+}
+
+#[test]
+fn test_feed_state_store_escape() {
+    let d = tempdir().unwrap();
+    let feed = OutFeed::default();
+    let uri = "https://example.com/feed\twith\nbreaks\\";
+    let mut state = FeedState::default();
+    state.etag = Some(String::from("\"x\ty\""));
+    let mut store = StateStore::new(d.path().to_path_buf()).unwrap();
+    store.set(uri, state.clone(), &feed).unwrap();
+    store.set("https://example.com/other", FeedState::default(), &feed).unwrap();
+    store.save().unwrap();
+
+    let store = StateStore::new(d.path().to_path_buf()).unwrap();
+    assert_eq!(store.get(uri), state);
+    assert!(store.feed(uri).is_some());
+}
+
+struct GetOnly;
+
+impl FeedGet for GetOnly {
+    fn get(&self, _s: &str, _method: Option<FeedMethod>) -> Result<OutFeed, crate::Error> {
+        Ok(OutFeed::default())
+    }
+}
+
+#[test]
+fn test_feed_get_state_default() {
+    let mut state = FeedState::default();
+    state.etag = Some(String::from("foo"));
+    let feed = GetOnly{}.get_state("foo", Some(FeedMethod::Update), &mut state).unwrap();
+    assert!(feed.is_some());
+    assert_eq!(state, FeedState::default());
+}
+
 #[test]
 #[cfg(feature = "fs")]
 fn test_sequence_order() {
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     let mut entry: Entry;
     let mut s: String;
     let mut r: Vec<u8>;
@@ -220,6 +965,124 @@ fn test_sequence_order() {
     check_xml_title(r, "clyde");
 }
 
+fn bounds_entry(id: &str, title: &str, date: &str) -> Entry {
+    let mut entry = Entry::default();
+    entry.id = String::from(id);
+    entry.title = Text::plain(String::from(title));
+    entry.published = Some(DateTime::parse_from_rfc3339(date).unwrap().into());
+    entry
+}
+
+#[test]
+fn test_sequence_bounds() {
+    let mut seq = Sequencer::new(vec!());
+    let mut r: Vec<u8>;
+
+    seq.add(bounds_entry("a", "inky", "2024-01-01T00:00:00+00:00"));
+    seq.add(bounds_entry("b", "pinky", "2024-02-01T00:00:00+00:00"));
+    seq.add(bounds_entry("c", "blinky", "2024-03-01T00:00:00+00:00"));
+    seq.add(bounds_entry("d", "clyde", "2024-04-01T00:00:00+00:00"));
+    assert_eq!(seq.by_ref().count(), 4);
+
+    seq.set_max_entries(2);
+    r = seq.next().unwrap();
+    check_xml_title(r, "blinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "clyde");
+    assert!(seq.next().is_none());
+
+    seq.set_max_entries(10);
+    seq.set_since(DateTime::parse_from_rfc3339("2024-02-01T00:00:00+00:00").unwrap());
+    seq.set_until(DateTime::parse_from_rfc3339("2024-03-15T00:00:00+00:00").unwrap());
+    r = seq.next().unwrap();
+    check_xml_title(r, "pinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "blinky");
+    assert!(seq.next().is_none());
+
+    seq.set_max_age(TimeDelta::days(1));
+    assert_eq!(seq.by_ref().count(), 0);
+    seq.add(bounds_entry("e", "sue", Local::now().to_rfc3339().as_str()));
+    seq.set_until(Local::now().fixed_offset());
+    assert_eq!(seq.to_feed().unwrap().entries.len(), 1);
+}
+
+#[test]
+fn test_sequence_order_key() {
+    let mut seq = Sequencer::new(vec!());
+    let mut entry: Entry;
+    let mut r: Vec<u8>;
+
+    // seen times are given explicitly, as consecutive clock readings may tie.
+    entry = bounds_entry("a", "inky", "2024-01-01T00:00:00+00:00");
+    entry.updated = DateTime::parse_from_rfc3339("2024-05-01T00:00:00+00:00").unwrap();
+    seq.restore(CachedEntry{
+        entry: entry,
+        source: String::new(),
+        seen: DateTime::parse_from_rfc3339("2024-06-02T00:00:00+00:00").unwrap(),
+    });
+    entry = bounds_entry("b", "pinky", "2024-02-01T00:00:00+00:00");
+    entry.updated = DateTime::parse_from_rfc3339("2024-02-01T00:00:00+00:00").unwrap();
+    seq.restore(CachedEntry{
+        entry: entry,
+        source: String::new(),
+        seen: DateTime::parse_from_rfc3339("2024-06-01T00:00:00+00:00").unwrap(),
+    });
+    seq.restore(CachedEntry{
+        entry: bounds_entry("c", "blinky", "2024-03-01T00:00:00+00:00"),
+        source: String::new(),
+        seen: DateTime::parse_from_rfc3339("2024-06-03T00:00:00+00:00").unwrap(),
+    });
+
+    seq.set_order(SequencerOrder::NewestFirst);
+    r = seq.next().unwrap();
+    check_xml_title(r, "blinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "pinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "inky");
+    assert!(seq.next().is_none());
+
+    seq.set_key(SequencerKey::Updated);
+    r = seq.next().unwrap();
+    check_xml_title(r, "inky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "pinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "blinky");
+    assert!(seq.next().is_none());
+
+    seq.set_max_entries(1);
+    seq.set_order(SequencerOrder::OldestFirst);
+    r = seq.next().unwrap();
+    check_xml_title(r, "inky");
+    assert!(seq.next().is_none());
+
+    seq.set_max_entries(3);
+    seq.set_key(SequencerKey::Seen);
+    r = seq.next().unwrap();
+    check_xml_title(r, "pinky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "inky");
+    r = seq.next().unwrap();
+    check_xml_title(r, "blinky");
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_error() {
+    let fs = FsFeed{};
+    let e = fs.get("testdata/nonexistent.xml", None).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IoError);
+    assert_eq!(e.uri().unwrap(), "testdata/nonexistent.xml");
+    assert!(std::error::Error::source(&e).is_some());
+    assert!(e.to_string().starts_with("i/o failure for testdata/nonexistent.xml: "));
+
+    let e = fs.get("Cargo.toml", None).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::ParseError);
+    assert_eq!(e.uri().unwrap(), "Cargo.toml");
+}
+
 #[test]
 fn test_meta() {
     let mut o = FeedMetadata::default();
@@ -256,8 +1119,151 @@ let fs = FsFeed{};
     let fs = FsFeed{};
 
     let feed = fs.get("testdata/test.rss.xml", None).unwrap();
-    let mut seq = Sequencer::new();
+    let mut seq = Sequencer::new(vec!());
     seq = seq.with_cache(&mut cache);
     
     seq.add_from(feed);
 }
+
+/// serve the given raw responses on loopback, one per connection, and return the base uri.
+#[cfg(feature = "http")]
+fn serve_http(responses: Vec<Vec<u8>>) -> String {
+    let srv = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = srv.local_addr().unwrap();
+    thread::spawn(move || {
+        for v in responses {
+            let (mut conn, _) = srv.accept().unwrap();
+            let mut r = BufReader::new(conn.try_clone().unwrap());
+            let mut l = String::new();
+            loop {
+                l.clear();
+                r.read_line(&mut l).unwrap();
+                if l.trim().is_empty() {
+                    break;
+                }
+            }
+            conn.write_all(v.as_slice()).unwrap();
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[cfg(feature = "http")]
+fn http_response(status: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+    let mut r = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for v in headers {
+        r.push_str(v);
+        r.push_str("\r\n");
+    }
+    r.push_str("\r\n");
+    let mut b: Vec<u8> = r.into();
+    b.extend_from_slice(body);
+    b
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn test_http_get() {
+    let mut b: Vec<u8> = Vec::new();
+    File::open("testdata/test.atom.xml").unwrap().read_to_end(&mut b).unwrap();
+    let uri = serve_http(vec!(
+        http_response("200 OK", &["Content-Type: application/atom+xml"], b.as_slice()),
+    ));
+    let feed = HttpFeed::new().get(format!("{}/feed.xml", uri).as_str(), None).unwrap();
+    let mut seq = Sequencer::new(vec!());
+    assert_eq!(seq.add_from(feed), 16);
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn test_http_get_redirect() {
+    let mut b: Vec<u8> = Vec::new();
+    File::open("testdata/test.rss.xml").unwrap().read_to_end(&mut b).unwrap();
+    let target = serve_http(vec!(
+        http_response("200 OK", &["Content-Type: application/rss+xml; charset=utf-8"], b.as_slice()),
+    ));
+    let location = format!("Location: {}/moved.xml", target);
+    let uri = serve_http(vec!(
+        http_response("301 Moved Permanently", &[location.as_str()], b""),
+    ));
+    HttpFeed::new().get(format!("{}/feed.xml", uri).as_str(), None).unwrap();
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn test_http_get_fail() {
+    let uri = serve_http(vec!(
+        http_response("404 Not Found", &["Content-Type: text/plain"], b"nope"),
+        http_response("200 OK", &["Content-Type: text/html"], b"<html><body>nope</body></html>"),
+    ));
+    let fetcher = HttpFeed::new();
+    match fetcher.get(format!("{}/feed.xml", uri).as_str(), None) {
+        Ok(_v) => {
+            panic!("expected not found");
+        },
+        Err(e) => {
+            assert_eq!(e.kind(), ErrorKind::StatusError(404));
+            assert!(e.uri().unwrap().ends_with("/feed.xml"));
+        },
+    };
+    match fetcher.get(format!("{}/feed.xml", uri).as_str(), None) {
+        Ok(_v) => {
+            panic!("expected content type rejection");
+        },
+        Err(e) => {
+            assert_eq!(e.kind(), ErrorKind::ParseError);
+        },
+    };
+    assert!(fetcher.get("ftp://localhost/feed.xml", None).is_err());
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn test_http_get_conditional() {
+    let mut b: Vec<u8> = Vec::new();
+    let mut state = FeedState::default();
+    File::open("testdata/test.atom.xml").unwrap().read_to_end(&mut b).unwrap();
+    let uri = serve_http(vec!(
+        http_response("200 OK", &["Content-Type: application/atom+xml", "ETag: \"foo\"", "Last-Modified: Tue, 25 Jun 2024 18:46:00 GMT"], b.as_slice()),
+        http_response("304 Not Modified", &["ETag: \"bar\""], b""),
+        http_response("200 OK", &["Content-Type: application/atom+xml"], b.as_slice()),
+    ));
+    let uri = format!("{}/feed.xml", uri);
+    let fetcher = HttpFeed::new();
+
+    let feed = fetcher.get_state(uri.as_str(), Some(FeedMethod::Update), &mut state).unwrap();
+    assert_eq!(feed.unwrap().entries.len(), 16);
+    assert_eq!(state.etag.as_ref().unwrap(), "\"foo\"");
+    assert_eq!(state.last_modified.as_ref().unwrap(), "Tue, 25 Jun 2024 18:46:00 GMT");
+
+    // validators sent along with not modified are kept.
+    let feed = fetcher.get_state(uri.as_str(), Some(FeedMethod::Update), &mut state).unwrap();
+    assert!(feed.is_none());
+    assert_eq!(state.etag.as_ref().unwrap(), "\"bar\"");
+    assert_eq!(state.last_modified.as_ref().unwrap(), "Tue, 25 Jun 2024 18:46:00 GMT");
+
+    // same content without validators is still detected by digest
+    let feed = fetcher.get_state(uri.as_str(), Some(FeedMethod::Update), &mut state).unwrap();
+    assert!(feed.is_none());
+    assert!(state.etag.is_none());
+}
+
+#[test]
+fn test_opml() {
+    let mut b: Vec<u8>;
+    let sources = opml::from_file("testdata/test.opml").unwrap();
+
+    assert_eq!(sources.len(), 4);
+    assert_eq!(sources[0].url, "https://example.com/inky.xml");
+    assert_eq!(sources[0].html_url.as_ref().unwrap(), "https://example.com/inky/");
+    assert!(sources[0].groups.is_empty());
+    assert_eq!(sources[1].title.as_ref().unwrap(), "Pinky & co");
+    assert_eq!(sources[1].groups, vec!("pac"));
+    assert_eq!(sources[2].url, "https://example.com/blinky.xml");
+    assert_eq!(sources[2].groups, vec!("pac", "man"));
+    assert_eq!(sources[3].groups, vec!("pac"));
+
+    b = Vec::new();
+    opml::write_to("ghosts", sources.as_slice(), &mut b).unwrap();
+    assert_eq!(opml::from_bytes(b.as_slice()).unwrap(), sources);
+}
@@ -0,0 +1,320 @@
+use std::fs::read_to_string;
+
+use serde::Deserialize;
+
+use atom_syndication::Feed;
+use atom_syndication::Entry;
+use atom_syndication::Category;
+use atom_syndication::Text;
+use atom_syndication::FixedDateTime;
+
+use crier::opml::OpmlSource;
+use crier::DateFallback;
+#[cfg(feature = "sanitize")]
+pub use crier::sanitize::Sanitizer;
+use crier::Error;
+use crier::ErrorKind;
+
+
+/// Aggregate settings read from a TOML configuration file.
+///
+/// Every setting is optional, and the command line option of the same name takes precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub id: Option<String>,
+    pub output: Option<String>,
+    pub format: Option<String>,
+    pub cache_dir: Option<String>,
+    pub cache_db: Option<String>,
+    pub state_dir: Option<String>,
+    pub max_entries: Option<usize>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub order: Option<String>,
+    pub sort_key: Option<String>,
+    pub provenance: Option<bool>,
+    pub sanitize: Option<bool>,
+    pub date_fallback: Option<DateFallback>,
+    pub keep_entries: Option<usize>,
+    pub keep_days: Option<i64>,
+    pub keep_bytes: Option<usize>,
+    pub opml: Vec<String>,
+    pub export_opml: Option<String>,
+    #[serde(rename = "source")]
+    pub sources: Vec<SourceConfig>,
+}
+
+/// A source to aggregate, with the settings that apply to its entries only.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceConfig {
+    pub url: String,
+    /// Display name, replacing the title of the feed.
+    pub name: Option<String>,
+    pub html_url: Option<String>,
+    /// Groups to list the source under in OPML export.
+    pub groups: Vec<String>,
+    /// Skip entries that can not be read instead of failing the source.
+    pub allow_fail: bool,
+    /// Date to give items that have none: reject, channel, fetched or position, overriding the
+    /// aggregate setting.
+    pub date_fallback: Option<DateFallback>,
+    /// Only keep entries mentioning any of these words in title or summary.
+    pub include: Vec<String>,
+    /// Drop entries mentioning any of these words in title or summary.
+    pub exclude: Vec<String>,
+    /// Only keep this many of the most recent entries.
+    pub max_entries: Option<usize>,
+    /// Categories added to every entry.
+    pub categories: Vec<String>,
+    /// Remove unsafe markup from summaries and content, overriding the aggregate setting.
+    ///
+    /// All sources share the default allowlist of the sanitizer, which can not be changed per
+    /// source.
+    pub sanitize: Option<bool>,
+}
+
+impl SourceConfig {
+    pub fn new(url: &str) -> SourceConfig {
+        let mut o = SourceConfig::default();
+        o.url = String::from(url);
+        o
+    }
+
+    pub fn to_opml(&self) -> OpmlSource {
+        let mut o = OpmlSource::new(self.url.as_str());
+        o.title = self.name.clone();
+        o.html_url = self.html_url.clone();
+        o.groups = self.groups.clone();
+        o
+    }
+
+    fn matches(&self, entry: &Entry, words: &[String]) -> bool {
+        let mut s: String;
+
+        s = entry.title.value.to_lowercase();
+        match &entry.summary {
+            Some(v) => {
+                s.push(' ');
+                s.push_str(v.value.to_lowercase().as_str());
+            },
+            None => {},
+        };
+        words.iter().any(|v| s.contains(v.to_lowercase().as_str()))
+    }
+
+    /// Apply the name, filters, limit, categories and sanitizing of the source to a feed
+    /// retrieved from it.
+    pub fn apply(&self, feed: &mut Feed, sanitizer: &Sanitizer) {
+        let mut entries: Vec<Entry>;
+
+        match &self.name {
+            Some(v) => {
+                feed.set_title(Text::plain(v.as_str()));
+            },
+            None => {},
+        };
+
+        entries = feed.entries().to_vec();
+        if !self.include.is_empty() {
+            entries.retain(|v| self.matches(v, &self.include));
+        }
+        if !self.exclude.is_empty() {
+            entries.retain(|v| !self.matches(v, &self.exclude));
+        }
+        match self.max_entries {
+            Some(v) => {
+                entries.sort_by(|a, b| entry_date(b).cmp(&entry_date(a)));
+                entries.truncate(v);
+            },
+            None => {},
+        };
+        for v in entries.iter_mut() {
+            for c in self.categories.iter() {
+                if v.categories.iter().any(|vv| vv.term == *c) {
+                    continue;
+                }
+                let mut category = Category::default();
+                category.set_term(c.as_str());
+                v.categories.push(category);
+            }
+        }
+        feed.set_entries(entries);
+        if self.sanitize == Some(true) {
+            sanitizer.apply(feed);
+        }
+    }
+}
+
+/// Stands in for the sanitizer when built without the sanitize feature, in which case sources
+/// asking to be sanitized are refused before any feed is retrieved.
+#[cfg(not(feature = "sanitize"))]
+pub struct Sanitizer;
+
+#[cfg(not(feature = "sanitize"))]
+impl Sanitizer {
+    pub fn new() -> Sanitizer {
+        Sanitizer
+    }
+
+    pub fn apply(&self, _feed: &mut Feed) {
+    }
+}
+
+impl From<OpmlSource> for SourceConfig {
+    fn from(v: OpmlSource) -> SourceConfig {
+        let mut o = SourceConfig::new(v.url.as_str());
+        o.name = v.title;
+        o.html_url = v.html_url;
+        o.groups = v.groups;
+        o
+    }
+}
+
+fn entry_date(entry: &Entry) -> FixedDateTime {
+    match entry.published {
+        Some(v) => {
+            v
+        },
+        None => {
+            entry.updated
+        },
+    }
+}
+
+/// Read aggregate settings from a TOML file.
+pub fn from_file(fp: &str) -> Result<FileConfig, Error> {
+    let s: String;
+
+    match read_to_string(fp) {
+        Ok(v) => {
+            s = v;
+        },
+        Err(e) => {
+            return Err(Error::from(e).with_uri(fp));
+        },
+    };
+    match toml::from_str(s.as_str()) {
+        Ok(v) => {
+            Ok(v)
+        },
+        Err(e) => {
+            Err(Error::new(ErrorKind::ParseError).with_uri(fp).with_source(e))
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use atom_syndication::Feed;
+    use atom_syndication::Entry;
+    use atom_syndication::Category;
+    use atom_syndication::Text;
+    use chrono::DateTime;
+
+    use super::SourceConfig;
+    use super::Sanitizer;
+
+    fn entry(id: &str, title: &str, date: &str) -> Entry {
+        let mut o = Entry::default();
+        o.set_id(id);
+        o.set_title(Text::plain(title));
+        o.set_updated(DateTime::parse_from_rfc3339(date).unwrap());
+        o
+    }
+
+    fn feed() -> Feed {
+        let mut o = Feed::default();
+        o.set_title(Text::plain("original"));
+        o.set_entries(vec!(
+            entry("a", "Rust release", "2024-06-01T00:00:00+00:00"),
+            entry("b", "Weather report", "2024-06-03T00:00:00+00:00"),
+            entry("c", "rust nightly weather", "2024-06-02T00:00:00+00:00"),
+        ));
+        o
+    }
+
+    fn ids(feed: &Feed) -> Vec<&str> {
+        feed.entries().iter().map(|v| v.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_source_apply_name() {
+        let mut src = SourceConfig::new("http://localhost/feed");
+        let mut o = feed();
+
+        src.apply(&mut o, &Sanitizer::new());
+        assert_eq!(o.title.value, "original");
+        assert_eq!(ids(&o), vec!("a", "b", "c"));
+
+        src.name = Some(String::from("renamed"));
+        src.apply(&mut o, &Sanitizer::new());
+        assert_eq!(o.title.value, "renamed");
+    }
+
+    #[test]
+    fn test_source_apply_filter() {
+        let mut src = SourceConfig::new("http://localhost/feed");
+        let mut o = feed();
+
+        src.include = vec!(String::from("RUST"));
+        src.apply(&mut o, &Sanitizer::new());
+        assert_eq!(ids(&o), vec!("a", "c"));
+
+        let mut o = feed();
+        src.exclude = vec!(String::from("weather"));
+        src.apply(&mut o, &Sanitizer::new());
+        assert_eq!(ids(&o), vec!("a"));
+
+        let mut o = feed();
+        o.entries[0].set_summary(Some(Text::plain("about the weather")));
+        src.apply(&mut o, &Sanitizer::new());
+        assert!(ids(&o).is_empty());
+    }
+
+    #[test]
+    fn test_source_apply_max_entries() {
+        let mut src = SourceConfig::new("http://localhost/feed");
+        let mut o = feed();
+
+        src.max_entries = Some(2);
+        src.apply(&mut o, &Sanitizer::new());
+        assert_eq!(ids(&o), vec!("b", "c"));
+    }
+
+    #[test]
+    fn test_source_apply_categories() {
+        let mut src = SourceConfig::new("http://localhost/feed");
+        let mut o = feed();
+        let mut category = Category::default();
+
+        category.set_term("news");
+        o.entries[1].categories.push(category);
+        src.categories = vec!(String::from("news"), String::from("tech"));
+        src.apply(&mut o, &Sanitizer::new());
+        for v in o.entries() {
+            let terms: Vec<&str> = v.categories.iter().map(|c| c.term.as_str()).collect();
+            assert_eq!(terms, vec!("news", "tech"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "sanitize")]
+    fn test_source_apply_sanitize() {
+        let mut src = SourceConfig::new("http://localhost/feed");
+        let sanitizer = Sanitizer::new();
+        let mut o = feed();
+
+        o.entries[0].set_summary(Some(Text::html("inky<script>evil()</script>")));
+        src.apply(&mut o, &sanitizer);
+        assert_eq!(o.entries[0].summary.as_ref().unwrap().value, "inky<script>evil()</script>");
+
+        src.sanitize = Some(true);
+        src.apply(&mut o, &sanitizer);
+        assert_eq!(o.entries[0].summary.as_ref().unwrap().value, "inky");
+    }
+}
@@ -1,38 +1,120 @@
 use std::path::absolute;
 use std::path::PathBuf;
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+use std::fs::create_dir_all;
 use std::process;
 use std::io::stdout;
+use std::io::Write;
 use std::str::from_utf8;
+use std::error::Error as StdError;
 
 use uuid::Uuid;
 
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::TimeDelta;
+
+use atom_syndication::Feed;
+
 use clap::Arg;
 use clap::App;
+use clap::ArgMatches;
 
 use log::debug;
 use log::info;
 use env_logger;
 
 use crier::Sequencer;
+use crier::SequencerOrder;
+use crier::SequencerKey;
 use crier::io::FeedGet;
+use crier::io::FeedPut;
+use crier::io::FeedMethod;
+use crier::io::FeedState;
+use crier::io::state::StateStore;
 use crier::mem::MemCache;
+use crier::io::fs::FsCache;
+#[cfg(feature = "sqlite")]
+use crier::io::sqlite::SqliteCache;
+use crier::cache::retention::RetentionPolicy;
+use crier::cache::retention::MaxEntries;
+use crier::cache::retention::MaxAge;
+use crier::cache::retention::MaxBytes;
 use crier::io::fs::FsFeed;
+use crier::io::fs::FsFeedReader;
+#[cfg(feature = "http")]
+use crier::io::http::HttpFeed;
+#[cfg(feature = "http")]
+use crier::io::http::is_http;
+use crier::opml;
+use crier::opml::OpmlSource;
 use crier::Error;
+use crier::ErrorKind;
+use crier::DateFallback;
+
+mod config;
+use config::FileConfig;
+use config::SourceConfig;
+use config::Sanitizer;
+
+enum OutputFormat {
+    Atom,
+    Rss,
+    Json,
+}
 
 struct Config {
-    urls: Vec<String>,
+    sources: Vec<SourceConfig>,
+    opml: Vec<String>,
+    export_opml: Option<String>,
     author: String,
     title: String,
     id: String,
+    state_dir: Option<String>,
+    cache_dir: Option<String>,
+    cache_db: Option<String>,
+    output: Option<String>,
+    format: OutputFormat,
+    max_entries: Option<usize>,
+    keep_entries: Option<usize>,
+    keep_days: Option<i64>,
+    keep_bytes: Option<usize>,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    order: SequencerOrder,
+    key: SequencerKey,
+    provenance: bool,
+    sanitize: bool,
+    date_fallback: DateFallback,
 }
 
 impl Config {
-    fn new(id: String, title: String, author: String, urls: Vec<String>) -> Config {
+    fn new(id: String, title: String, author: String, sources: Vec<SourceConfig>) -> Config {
         Config {
-            urls: urls,
+            sources: sources,
+            opml: Vec::new(),
+            export_opml: None,
             title: title,
             author: author,
             id: id,
+            state_dir: None,
+            cache_dir: None,
+            cache_db: None,
+            output: None,
+            format: OutputFormat::Atom,
+            max_entries: None,
+            keep_entries: None,
+            keep_days: None,
+            keep_bytes: None,
+            since: None,
+            until: None,
+            order: SequencerOrder::OldestFirst,
+            key: SequencerKey::Published,
+            provenance: false,
+            sanitize: false,
+            date_fallback: DateFallback::Reject,
         }
     }
 }
@@ -42,13 +124,20 @@ fn parse() -> Config {
     .version(env!("CARGO_PKG_VERSION"))
     .author(env!("CARGO_PKG_AUTHORS"));
 
+    o = o.arg(
+        Arg::with_name("config")
+            .long("config")
+            .short("c")
+            .value_name("TOML file with aggregate and source settings")
+            .takes_value(true)
+    );
+
     o = o.arg(
         Arg::with_name("title")
             .long("title")
             .short("t")
             .value_name("Aggregated feed title")
             .takes_value(true)
-            .required(true)
     );
 
     o = o.arg(
@@ -57,7 +146,6 @@ fn parse() -> Config {
             .short("a")
             .value_name("Aggregated feed author")
             .takes_value(true)
-            .required(true)
     );
 
     // TODO: implement auto generate id when missing
@@ -67,7 +155,161 @@ fn parse() -> Config {
             .short("i")
             .value_name("Aggregated feed id uuid value")
             .takes_value(true)
-            .required(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("cachedir")
+            .long("cache-dir")
+            .value_name("Directory to keep aggregated entries in across runs")
+            .takes_value(true)
+    );
+
+    #[cfg(feature = "sqlite")]
+    {
+        o = o.arg(
+            Arg::with_name("cachedb")
+                .long("cache-db")
+                .value_name("SQLite database to keep aggregated entries in across runs, instead of a cache directory")
+                .takes_value(true)
+                .conflicts_with("cachedir")
+        );
+    }
+
+    o = o.arg(
+        Arg::with_name("statedir")
+            .long("state-dir")
+            .value_name("Directory to keep source fetch state in (default: state in cache dir, or next to cache db)")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("File to write aggregated feed to instead of stdout")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("format")
+            .long("format")
+            .value_name("Output format")
+            .takes_value(true)
+            .possible_values(&["atom", "rss", "json"])
+    );
+
+    o = o.arg(
+        Arg::with_name("keepentries")
+            .long("keep-entries")
+            .value_name("Maximum number of most recent entries to keep in cache")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("keepdays")
+            .long("keep-days")
+            .value_name("Maximum age in days of entries to keep in cache")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("keepbytes")
+            .long("keep-bytes")
+            .value_name("Maximum total size of entries to keep in cache")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("maxentries")
+            .long("max-entries")
+            .value_name("Maximum number of most recent entries to output")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("since")
+            .long("since")
+            .value_name("Only output entries at or after date (RFC3339 or YYYY-MM-DD)")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("until")
+            .long("until")
+            .value_name("Only output entries at or before date (RFC3339 or YYYY-MM-DD)")
+            .takes_value(true)
+    );
+
+    o = o.arg(
+        Arg::with_name("order")
+            .long("order")
+            .value_name("Output order of entries")
+            .takes_value(true)
+            .possible_values(&["oldest", "newest"])
+    );
+
+    o = o.arg(
+        Arg::with_name("sortkey")
+            .long("sort-key")
+            .value_name("Entry date to order by")
+            .takes_value(true)
+            .possible_values(&["published", "updated", "seen"])
+    );
+
+    o = o.arg(
+        Arg::with_name("provenance")
+            .long("provenance")
+            .help("Record the originating feed of each entry as its atom:source")
+    );
+
+    o = o.arg(
+        Arg::with_name("noprovenance")
+            .long("no-provenance")
+            .help("Do not record the originating feed of entries, overriding the configuration file")
+            .conflicts_with("provenance")
+    );
+
+    #[cfg(feature = "sanitize")]
+    {
+        o = o.arg(
+            Arg::with_name("sanitize")
+                .long("sanitize")
+                .help("Remove scripts, event handlers, iframes and other unsafe markup from entries")
+        );
+    }
+
+    #[cfg(feature = "sanitize")]
+    {
+        o = o.arg(
+            Arg::with_name("nosanitize")
+                .long("no-sanitize")
+                .help("Do not remove unsafe markup from entries, overriding the configuration file")
+                .conflicts_with("sanitize")
+        );
+    }
+
+    o = o.arg(
+        Arg::with_name("datefallback")
+            .long("date-fallback")
+            .value_name("Date to give entries that have none")
+            .takes_value(true)
+            .possible_values(&["reject", "channel", "fetched", "position"])
+    );
+
+    o = o.arg(
+        Arg::with_name("opml")
+            .long("opml")
+            .value_name("OPML file to read sources from")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+    );
+
+    o = o.arg(
+        Arg::with_name("exportopml")
+            .long("export-opml")
+            .value_name("File to write aggregated http(s) sources to as OPML")
+            .takes_value(true)
     );
 
     o = o.arg(Arg::with_name("URLS")
@@ -76,42 +318,283 @@ fn parse() -> Config {
 
     let m = o.get_matches();
 
-    Config::new(
-        String::from(m.value_of("id").unwrap()),
-        String::from(m.value_of("title").unwrap()),
-        String::from(m.value_of("author").unwrap()),
-        m.values_of("URLS").unwrap().map(|v| String::from(v)).collect())
+    let file = match m.value_of("config") {
+        Some(v) => {
+            config::from_file(v).unwrap_or_else(|e| fail(e))
+        },
+        None => {
+            FileConfig::default()
+        },
+    };
+
+    let mut cfg = Config::new(
+        required(m.value_of("id"), file.id, "id"),
+        required(m.value_of("title"), file.title, "title"),
+        required(m.value_of("author"), file.author, "author"),
+        match m.values_of("URLS") {
+            Some(v) => {
+                v.map(|vv| SourceConfig::new(vv)).collect()
+            },
+            None => {
+                file.sources
+            },
+        });
+    cfg.opml = m.values_of("opml").map(|v| v.map(|vv| String::from(vv)).collect()).unwrap_or(file.opml);
+    cfg.export_opml = m.value_of("exportopml").map(|v| String::from(v)).or(file.export_opml);
+    if cfg.sources.len() == 0 && cfg.opml.len() == 0 {
+        eprintln!("no sources given, use uris, --opml or --config");
+        process::exit(1);
+    }
+    cfg.cache_dir = m.value_of("cachedir").map(|v| String::from(v));
+    cfg.cache_db = m.value_of("cachedb").map(|v| String::from(v));
+    if cfg.cache_dir.is_none() && cfg.cache_db.is_none() {
+        cfg.cache_dir = file.cache_dir;
+        cfg.cache_db = file.cache_db;
+    }
+    if cfg.cache_dir.is_some() && cfg.cache_db.is_some() {
+        eprintln!("use either a cache directory or a cache database, not both");
+        process::exit(1);
+    }
+    #[cfg(not(feature = "sqlite"))]
+    if cfg.cache_db.is_some() {
+        eprintln!("cache database not supported, build with the sqlite feature");
+        process::exit(1);
+    }
+    cfg.state_dir = match m.value_of("statedir").map(|v| String::from(v)).or(file.state_dir) {
+        Some(v) => {
+            Some(v)
+        },
+        None => {
+            match (&cfg.cache_dir, &cfg.cache_db) {
+                (Some(v), _) => {
+                    Some(String::from(PathBuf::from(v).join("state").to_string_lossy()))
+                },
+                (None, Some(v)) => {
+                    Some(String::from(PathBuf::from(v).with_extension("state").to_string_lossy()))
+                },
+                (None, None) => {
+                    None
+                },
+            }
+        },
+    };
+    cfg.output = m.value_of("output").map(|v| String::from(v)).or(file.output);
+    cfg.format = match m.value_of("format").or(file.format.as_deref()) {
+        Some("rss") => OutputFormat::Rss,
+        Some("json") => OutputFormat::Json,
+        Some("atom") | None => OutputFormat::Atom,
+        Some(v) => {
+            eprintln!("invalid format: {}", v);
+            process::exit(1);
+        },
+    };
+    cfg.max_entries = m.value_of("maxentries").map(|v| v.parse().unwrap_or_else(|e| {
+        eprintln!("invalid max entries: {}: {}", v, e);
+        process::exit(1);
+    })).or(file.max_entries);
+    cfg.keep_entries = m.value_of("keepentries").map(|v| v.parse().unwrap_or_else(|e| {
+        eprintln!("invalid keep entries: {}: {}", v, e);
+        process::exit(1);
+    })).or(file.keep_entries);
+    cfg.keep_days = m.value_of("keepdays").map(|v| v.parse().unwrap_or_else(|e| {
+        eprintln!("invalid keep days: {}: {}", v, e);
+        process::exit(1);
+    })).or(file.keep_days);
+    cfg.keep_bytes = m.value_of("keepbytes").map(|v| v.parse().unwrap_or_else(|e| {
+        eprintln!("invalid keep bytes: {}: {}", v, e);
+        process::exit(1);
+    })).or(file.keep_bytes);
+    cfg.order = match m.value_of("order").or(file.order.as_deref()) {
+        Some("newest") => SequencerOrder::NewestFirst,
+        Some("oldest") | None => SequencerOrder::OldestFirst,
+        Some(v) => {
+            eprintln!("invalid order: {}", v);
+            process::exit(1);
+        },
+    };
+    cfg.key = match m.value_of("sortkey").or(file.sort_key.as_deref()) {
+        Some("updated") => SequencerKey::Updated,
+        Some("seen") => SequencerKey::Seen,
+        Some("published") | None => SequencerKey::Published,
+        Some(v) => {
+            eprintln!("invalid sort key: {}", v);
+            process::exit(1);
+        },
+    };
+    cfg.provenance = switch(&m, "provenance", "noprovenance", file.provenance);
+    cfg.sanitize = switch(&m, "sanitize", "nosanitize", file.sanitize);
+    cfg.date_fallback = match m.value_of("datefallback") {
+        Some("channel") => DateFallback::Channel,
+        Some("fetched") => DateFallback::Fetched,
+        Some("position") => DateFallback::Position,
+        Some("reject") => DateFallback::Reject,
+        Some(v) => {
+            eprintln!("invalid date fallback: {}", v);
+            process::exit(1);
+        },
+        None => file.date_fallback.unwrap_or_default(),
+    };
+    cfg.since = m.value_of("since").or(file.since.as_deref()).map(|v| parse_time(v));
+    cfg.until = m.value_of("until").or(file.until.as_deref()).map(|v| parse_time(v));
+    cfg
 }
 
-fn add_feed(seq: &mut Sequencer, getter: impl FeedGet, uri: String) -> Result<i64, Error> {
-    match getter.get(uri.as_str(), None) {
-        Ok(v) => {
-            let r = seq.add_from(v);
-            info!("got {} results from {}", r, uri);
-            return Ok(r);
+/// the value of a flag that has an option to turn it off, falling back to the file setting.
+fn switch(m: &ArgMatches, on: &str, off: &str, file: Option<bool>) -> bool {
+    if m.is_present(on) {
+        return true;
+    }
+    if m.is_present(off) {
+        return false;
+    }
+    file.unwrap_or(false)
+}
+
+/// take the command line value, else the configuration file value, and exit if neither is set.
+fn required(arg: Option<&str>, file: Option<String>, name: &str) -> String {
+    match arg.map(|v| String::from(v)).or(file) {
+        Some(v) => {
+            v
         },
-        Err(e) => {
-            return Err(Error::ParseError);
+        None => {
+            eprintln!("missing {}, use --{} or set it in the configuration file", name, name);
+            process::exit(1);
         },
+    }
+}
+
+/// parse a date argument, either a full RFC3339 date or a day (taken as midnight UTC).
+fn parse_time(v: &str) -> DateTime<FixedOffset> {
+    match DateTime::parse_from_rfc3339(v) {
+        Ok(r) => {
+            return r;
+        },
+        Err(_e) => {},
+    };
+    match NaiveDate::parse_from_str(v, "%Y-%m-%d") {
+        Ok(r) => {
+            return r.and_hms_opt(0, 0, 0).unwrap().and_utc().fixed_offset();
+        },
+        Err(_e) => {},
     };
+    eprintln!("invalid date: {}", v);
+    process::exit(1);
 }
 
-fn process_entry(seq: &mut Sequencer, uri: String) -> Result<(), Error> {
+/// add the entries of the source, recording its title and website if not already known.
+fn add_feed(seq: &mut Sequencer, getter: impl FeedGet, uri: String, source: &mut SourceConfig, states: &mut Option<StateStore>, sanitizer: &Sanitizer) -> Result<i64, Error> {
+    let mut feed: Feed;
+    let mut state: FeedState;
+
+    match states {
+        Some(store) => {
+            state = store.get(uri.as_str());
+            match getter.get_state(uri.as_str(), Some(FeedMethod::Update), &mut state) {
+                Ok(Some(v)) => {
+                    store.set(uri.as_str(), state, &v)?;
+                    feed = v;
+                },
+                Ok(None) => {
+                    match store.feed(uri.as_str()) {
+                        Some(v) => {
+                            info!("{} unchanged, using previous entries", uri);
+                            store.update(uri.as_str(), state);
+                            feed = v;
+                        },
+                        None => {
+                            state = FeedState::default();
+                            match getter.get_state(uri.as_str(), Some(FeedMethod::Read), &mut state) {
+                                Ok(Some(v)) => {
+                                    store.set(uri.as_str(), state, &v)?;
+                                    feed = v;
+                                },
+                                Ok(None) => {
+                                    return Err(Error::new(ErrorKind::IncompleteError).with_uri(uri.as_str()));
+                                },
+                                Err(e) => {
+                                    return Err(e);
+                                },
+                            };
+                        },
+                    };
+                },
+                Err(e) => {
+                    return Err(e);
+                },
+            };
+        },
+        None => {
+            match getter.get(uri.as_str(), None) {
+                Ok(v) => {
+                    feed = v;
+                },
+                Err(e) => {
+                    return Err(e);
+                },
+            };
+        },
+    };
+
+    if source.name.is_none() && !feed.title.value.is_empty() {
+        source.name = Some(feed.title.value.clone());
+    }
+    if source.html_url.is_none() {
+        source.html_url = feed.links.iter().find(|v| v.rel == "alternate").map(|v| v.href.clone());
+    }
+
+    source.apply(&mut feed, sanitizer);
+    let r = seq.add_from_source(feed, uri.as_str());
+    info!("got {} results from {}", r, uri);
+    Ok(r)
+}
+
+/// without the http feature, http sources are only recognized to be rejected.
+#[cfg(not(feature = "http"))]
+fn is_http(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+fn process_entry(seq: &mut Sequencer, source: &mut SourceConfig, states: &mut Option<StateStore>, sanitizer: &Sanitizer) -> Result<(), Error> {
     let v: PathBuf;
     let fp: String;
-    let fs = FsFeed{};
+    let date_fallback = source.date_fallback.unwrap_or_default();
+    let fs = FsFeedReader::new().with_allow_entry_fail(source.allow_fail).with_date_fallback(date_fallback);
+    let uri = source.url.clone();
 
     debug!("processing {}", uri);
-    match absolute(uri) {
+    #[cfg(not(feature = "http"))]
+    if is_http(uri.as_str()) {
+        return Err(Error::new(ErrorKind::IoError).with_uri(uri.as_str()).with_source("http sources not supported, build with the http feature"));
+    }
+    #[cfg(feature = "http")]
+    if is_http(uri.as_str()) {
+        return match add_feed(seq, HttpFeed::new().with_allow_entry_fail(source.allow_fail).with_date_fallback(date_fallback), uri, source, states, sanitizer) {
+            Ok(_) => {
+                Ok(())
+            },
+            Err(e) => {
+                Err(e)
+            },
+        };
+    }
+
+    match absolute(&uri) {
         Ok(r) => {
-            fp = String::from(r.to_str().unwrap());
+            match r.to_str() {
+                Some(vv) => {
+                    fp = String::from(vv);
+                },
+                None => {
+                    return Err(Error::new(ErrorKind::IoError).with_uri(uri.as_str()).with_source("path is not valid unicode"));
+                },
+            };
         },
         Err(e) => {
-            return Err(Error::ParseError);
+            return Err(Error::from(e).with_uri(uri.as_str()));
         }
     };
 
-    match add_feed(seq, fs, fp) {
+    match add_feed(seq, fs, fp, source, states, sanitizer) {
         Ok(r) => {
             return Ok(());
         },
@@ -121,23 +604,270 @@ fn process_entry(seq: &mut Sequencer, uri: String) -> Result<(), Error> {
     }
 }
 
+fn write_output(seq: &mut Sequencer, format: &OutputFormat, w: impl Write) -> Result<usize, Error> {
+    match format {
+        OutputFormat::Atom => {
+            seq.write_to(w)
+        },
+        OutputFormat::Rss => {
+            seq.write_rss_to(w)
+        },
+        OutputFormat::Json => {
+            seq.write_json_to(w)
+        },
+    }
+}
+
+/// explain what the user can do about the error.
+fn hint(e: &Error) -> Option<&'static str> {
+    match e.kind() {
+        ErrorKind::StatusError(v) => {
+            if v == 404 || v == 410 {
+                return Some("check that the feed address is correct and still published");
+            }
+            if v >= 500 {
+                return Some("the server failed, try again later");
+            }
+            Some("the server refused the request")
+        },
+        ErrorKind::IoError => {
+            Some("check that the source exists, is readable and reachable")
+        },
+        ErrorKind::ParseError => {
+            Some("the source does not look like a valid feed, OPML or configuration file")
+        },
+        ErrorKind::IncompleteError => {
+            Some("the source lacks data required by crier")
+        },
+        ErrorKind::ExistsError => {
+            Some("remove it first or choose another location")
+        },
+        ErrorKind::CacheError => {
+            Some("check permissions and free space of the cache and state directories")
+        },
+        ErrorKind::WriteError => {
+            None
+        },
+    }
+}
+
+/// print the error with its causes and exit.
+fn fail(e: Error) -> ! {
+    report(&e);
+    process::exit(1);
+}
+
+/// print the error with its causes, and what can be done about it.
+fn report(e: &Error) {
+    let mut src: Option<&dyn StdError>;
+
+    eprintln!("crier: {}", e);
+    src = e.source();
+    while let Some(v) = src {
+        match v.source() {
+            Some(vv) => {
+                eprintln!("  caused by: {}", vv);
+            },
+            None => {},
+        };
+        src = v.source();
+    }
+    match hint(e) {
+        Some(v) => {
+            eprintln!("  hint: {}", v);
+        },
+        None => {},
+    };
+}
+
 fn main() {
-    let cfg = parse();
-    let mut cache = MemCache::new();
+    let cfg: Config;
+    let mut mem_cache = MemCache::new();
+    let mut fs_cache: Option<FsCache> = None;
+    #[cfg(feature = "sqlite")]
+    let mut sqlite_cache: Option<SqliteCache> = None;
+    let mut failed: usize;
+
+    env_logger::init();
+    cfg = parse();
+
+    match &cfg.cache_dir {
+        Some(v) => {
+            create_dir_all(v).unwrap_or_else(|e| fail(Error::new(ErrorKind::CacheError).with_uri(v).with_source(e)));
+            fs_cache = Some(FsCache::new(PathBuf::from(v)));
+        },
+        None => {},
+    };
+    #[cfg(feature = "sqlite")]
+    match &cfg.cache_db {
+        Some(v) => {
+            sqlite_cache = Some(SqliteCache::new(Path::new(v)).unwrap_or_else(|e| fail(e)));
+        },
+        None => {},
+    };
 
     let id: Vec<u8> = cfg.id.into();
-    let mut seq = Sequencer::new(id).with_cache(&mut cache);
+    let mut seq = Sequencer::new(id);
+    seq = match &mut fs_cache {
+        Some(v) => {
+            seq.with_cache(v)
+        },
+        None => {
+            seq.with_cache(&mut mem_cache)
+        },
+    };
+    #[cfg(feature = "sqlite")]
+    match &mut sqlite_cache {
+        Some(v) => {
+            seq = seq.with_cache(v);
+        },
+        None => {},
+    };
+
+    let r = seq.restore_cache().unwrap_or_else(|e| fail(e));
+    debug!("restored {} entries from cache", r);
 
     seq.set_title(cfg.title.as_str());
     seq.set_author(cfg.author.as_str());
 
-    env_logger::init();
+    seq.set_order(cfg.order);
+    seq.set_key(cfg.key);
+    seq.set_provenance(cfg.provenance);
+
+    match cfg.max_entries {
+        Some(v) => {
+            seq.set_max_entries(v);
+        },
+        None => {},
+    };
+    match cfg.since {
+        Some(v) => {
+            seq.set_since(v);
+        },
+        None => {},
+    };
+    match cfg.until {
+        Some(v) => {
+            seq.set_until(v);
+        },
+        None => {},
+    };
+
+    debug!("config has {} sources", cfg.sources.len());
+
+    let mut states = match cfg.state_dir {
+        Some(v) => {
+            Some(StateStore::new(PathBuf::from(v)).unwrap_or_else(|e| fail(e)))
+        },
+        None => {
+            None
+        },
+    };
 
-    debug!("config has {} uris", cfg.urls.len());
+    let mut sources: Vec<SourceConfig> = cfg.sources;
+    for v in cfg.opml.iter() {
+        sources.extend(opml::from_file(v).unwrap_or_else(|e| fail(e)).into_iter().map(|v| SourceConfig::from(v)));
+    }
+    #[cfg(not(feature = "sanitize"))]
+    if sources.iter().any(|v| v.sanitize.unwrap_or(cfg.sanitize)) {
+        eprintln!("sanitizing not supported, build with the sanitize feature");
+        process::exit(1);
+    }
+    let sanitizer = Sanitizer::new();
+    // a failing source is reported and skipped, so that the others are still aggregated.
+    failed = 0;
+    for v in sources.iter_mut() {
+        if v.sanitize.is_none() {
+            v.sanitize = Some(cfg.sanitize);
+        }
+        if v.date_fallback.is_none() {
+            v.date_fallback = Some(cfg.date_fallback);
+        }
+        match process_entry(&mut seq, v, &mut states, &sanitizer) {
+            Ok(_) => {},
+            Err(e) => {
+                report(&e);
+                failed += 1;
+            },
+        };
+    }
+    seq.sync().unwrap_or_else(|e| fail(e));
 
-    for v in cfg.urls {
-        process_entry(&mut seq, v).unwrap_or_else(|e| process::exit(1));
+    match &cfg.export_opml {
+        Some(v) => {
+            let mut b: Vec<u8> = Vec::new();
+            // local files mean nothing to readers of the exported list.
+            let r: Vec<OpmlSource> = sources.iter().filter(|v| is_http(v.url.as_str())).map(|v| v.to_opml()).collect();
+            opml::write_to(cfg.title.as_str(), r.as_slice(), &mut b).unwrap_or_else(|e| fail(e));
+            FsFeed{}.put_bytes(b.as_slice(), v.as_str(), Some(FeedMethod::Update)).unwrap_or_else(|e| fail(e));
+        },
+        None => {},
+    };
+
+    let mut policies: Vec<Box<dyn RetentionPolicy>> = Vec::new();
+    match cfg.keep_days {
+        Some(v) => {
+            match TimeDelta::try_days(v) {
+                Some(d) => {
+                    policies.push(Box::new(MaxAge::new(d)));
+                },
+                None => {
+                    eprintln!("invalid keep days: {}: out of range", v);
+                    process::exit(1);
+                },
+            };
+        },
+        None => {},
+    };
+    match cfg.keep_entries {
+        Some(v) => {
+            policies.push(Box::new(MaxEntries::new(v)));
+        },
+        None => {},
+    };
+    match cfg.keep_bytes {
+        Some(v) => {
+            policies.push(Box::new(MaxBytes::new(v)));
+        },
+        None => {},
+    };
+    if policies.len() > 0 {
+        let p: Vec<&dyn RetentionPolicy> = policies.iter().map(|v| v.as_ref()).collect();
+        let r = seq.gc(p.as_slice()).unwrap_or_else(|e| fail(e));
+        for v in r.iter() {
+            info!("evicted {}", v);
+        }
+        info!("evicted {} entries from cache", r.len());
     }
 
-    seq.write_to(stdout()).unwrap();
+    match states {
+        Some(v) => {
+            v.save().unwrap_or_else(|e| fail(e));
+        },
+        None => {},
+    };
+
+    match cfg.output {
+        Some(v) => {
+            match cfg.format {
+                OutputFormat::Atom => {
+                    let feed = seq.to_feed().unwrap_or_else(|e| fail(e));
+                    FsFeed{}.put(&feed, v.as_str(), Some(FeedMethod::Update)).unwrap_or_else(|e| fail(e));
+                },
+                _ => {
+                    let mut b: Vec<u8> = Vec::new();
+                    write_output(&mut seq, &cfg.format, &mut b).unwrap_or_else(|e| fail(e));
+                    FsFeed{}.put_bytes(b.as_slice(), v.as_str(), Some(FeedMethod::Update)).unwrap_or_else(|e| fail(e));
+                },
+            };
+        },
+        None => {
+            write_output(&mut seq, &cfg.format, stdout()).unwrap_or_else(|e| fail(e));
+        },
+    };
+
+    if failed > 0 {
+        eprintln!("crier: {} of {} sources failed", failed, sources.len());
+        process::exit(1);
+    }
 }